@@ -0,0 +1,313 @@
+// A5/1: three LFSRs with majority clocking, the GSM stream cipher
+
+use crate::base_cryptography::{Key, Cipher};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::registry::CipherDescriptor;
+use crate::stream;
+
+/// The most steps `show key` traces, one line each.
+pub const MAX_STEPS: usize = 4096;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "a5",
+    aliases: &["a51"],
+    key_type: "key frame number",
+    description: "the GSM stream cipher, three registers moved by majority vote",
+    build: || Box::new(A5Cipher {
+        key: None,
+        steps: 16,
+        message: "".to_string()
+    }),
+};
+
+/// One register of A5/1, shifted towards its most significant bit.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Register {
+    pub length: usize,
+    /// Bits XORed into the new least significant bit.
+    pub taps: u32,
+    /// The bit voting for the majority.
+    pub clock: usize,
+    pub state: u32,
+}
+
+impl Register {
+    fn new(length: usize, taps: &[usize], clock: usize) -> Register {
+        Register {
+            length,
+            taps: taps.iter().fold(0, |acc, t| acc | 1 << t),
+            clock,
+            state: 0
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        (1 << self.length) - 1
+    }
+
+    pub fn clock_bit(&self) -> u8 {
+        ((self.state >> self.clock) & 1) as u8
+    }
+
+    pub fn output(&self) -> u8 {
+        ((self.state >> (self.length - 1)) & 1) as u8
+    }
+
+    /// Shift the register, XORing `bit` into the new least significant bit.
+    fn shift(&mut self, bit: u8) {
+        let feedback = (self.state & self.taps).count_ones() as u8 & 1;
+        self.state = ((self.state << 1) & self.mask()) | (feedback ^ bit) as u32;
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{:0width$b}", self.state, width = self.length)
+    }
+}
+
+fn majority(a: u8, b: u8, c: u8) -> u8 {
+    (a & b) | (a & c) | (b & c)
+}
+
+/// The three registers of A5/1, after the key and the frame number are loaded.
+#[derive(PartialEq, Debug, Clone)]
+pub struct A5 {
+    pub registers: [Register; 3],
+}
+
+impl A5 {
+    /// The key bits are read from the first byte, least significant bit first.
+    pub fn new(key: [u8; 8], frame: u32) -> A5 {
+        let mut a5 = A5 {
+            registers: [
+                Register::new(19, &[18, 17, 16, 13], 8),
+                Register::new(22, &[21, 20], 10),
+                Register::new(23, &[22, 21, 20, 7], 10),
+            ]
+        };
+        for i in 0..64 {
+            a5.load((key[i / 8] >> (i % 8)) & 1);
+        }
+        for i in 0..22 {
+            a5.load(((frame >> i) & 1) as u8);
+        }
+        for _ in 0..100 {
+            a5.step();
+        }
+        a5
+    }
+
+    /// Clock every register, XORing `bit` into each of them.
+    fn load(&mut self, bit: u8) {
+        for r in self.registers.iter_mut() {
+            r.shift(bit);
+        }
+    }
+
+    fn majority(&self) -> u8 {
+        let [r1, r2, r3] = &self.registers;
+        majority(r1.clock_bit(), r2.clock_bit(), r3.clock_bit())
+    }
+
+    /// Clock the registers agreeing with the majority; gives which ones moved.
+    pub fn step(&mut self) -> [bool; 3] {
+        let majority = self.majority();
+        let mut moved = [false; 3];
+        for (r, m) in self.registers.iter_mut().zip(moved.iter_mut()) {
+            if r.clock_bit() == majority {
+                r.shift(0);
+                *m = true;
+            }
+        }
+        moved
+    }
+
+    pub fn output(&self) -> u8 {
+        self.registers.iter().fold(0, |acc, r| acc ^ r.output())
+    }
+
+    /// One line per step: the registers, the majority, those clocked and the
+    /// output bit, starting with the current state.
+    pub fn trace(&mut self, steps: usize) -> Vec<String> {
+        let mut lines = vec![
+            format!("step {:<19} {:<22} {:<23} maj moved out", "R1", "R2", "R3"),
+            format!("{:<4} {} {} {}", 0, self.registers[0].describe(), self.registers[1].describe(), self.registers[2].describe()),
+        ];
+        for i in 1..=steps {
+            let majority = self.majority();
+            let moved = self.step();
+            lines.push(format!("{:<4} {} {} {} {:<3} {:<5} {}",
+                i,
+                self.registers[0].describe(),
+                self.registers[1].describe(),
+                self.registers[2].describe(),
+                majority,
+                moved.iter().enumerate().filter(|(_, m)| **m).map(|(j, _)| (j + 1).to_string()).collect::<String>(),
+                self.output()));
+        }
+        lines
+    }
+}
+
+impl Iterator for A5 {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        self.step();
+        Some(self.output())
+    }
+}
+
+/// The 64 bit session key, written as its 8 bytes, and the 22 bit frame number.
+type Session = (u64, u32);
+
+impl Key for Session {
+    fn parse(value: &KeyValue) -> Option<Session> {
+        match value {
+            KeyValue::Framed(key, frame) if *frame < 1 << 22 => Some((*key, *frame as u32)),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{:#018x} frame {:#x}", self.0, self.1)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct A5Cipher {
+    pub key: Option<Session>,
+    /// Number of steps shown by `show key`.
+    pub steps: usize,
+    pub message: String
+}
+
+impl A5Cipher {
+    fn a5(&self) -> Result<A5, CryptoError> {
+        self.key.map(|(k, f)| A5::new(k.to_be_bytes(), f)).ok_or(CryptoError::EmptyKey)
+    }
+}
+
+impl Cipher for A5Cipher {
+    fn name(&self) -> &'static str {
+        "a5"
+    }
+
+    fn title(&self) -> &'static str {
+        "A5/1"
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        Ok(stream::encrypt(msg, self.a5()?))
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        stream::decrypt(ct, self.a5()?)
+    }
+
+    fn keystream(&self) -> Result<Box<dyn Iterator<Item = u8>>, CryptoError> {
+        Ok(Box::new(self.a5()?))
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        Ok(Box::new(A5Cipher {
+            key: Some(Session::from_key(self.name(), key)?),
+            ..self.clone()
+        }))
+    }
+
+    fn describe(&self) -> String {
+        match self.key {
+            Some(key) => key.describe(),
+            None => "[not defined]".to_string()
+        }
+    }
+
+    fn show_key(&self) -> String {
+        match self.a5() {
+            Ok(mut a5) => a5.trace(self.steps).join("\n"),
+            Err(e) => e.to_string()
+        }
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(A5Cipher {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn set_option(&self, option: &str, value: &str) -> Result<Box<dyn Cipher>, CryptoError> {
+        match (option, value.parse::<usize>()) {
+            ("steps", Ok(steps)) if steps <= MAX_STEPS => Ok(Box::new(A5Cipher { steps, ..self.clone() })),
+            _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+        }
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![("steps", self.steps.to_string())]
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 8] = [0x12, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF];
+    const FRAME: u32 = 0x134;
+
+    /// 114 bits, packed with the first bit as the most significant.
+    fn burst(bits: &mut A5) -> Vec<u8> {
+        let mut burst = bits.take(114).chain(std::iter::repeat(0));
+        (0..15).map(|_| stream::pack(&mut burst)).collect()
+    }
+
+    #[test]
+    fn test_reference_vectors() {
+        let mut a5 = A5::new(KEY, FRAME);
+        assert_eq!(
+            burst(&mut a5),
+            vec![0x53, 0x4E, 0xAA, 0x58, 0x2F, 0xE8, 0x15, 0x1A, 0xB6, 0xE1, 0x85, 0x5A, 0x72, 0x8C, 0x00]);
+        assert_eq!(
+            burst(&mut a5),
+            vec![0x24, 0xFD, 0x35, 0xA3, 0x5D, 0x5F, 0xB6, 0x52, 0x6D, 0x32, 0xF9, 0x06, 0xDF, 0x1A, 0xC0]);
+    }
+
+    #[test]
+    fn test_majority_clocking() {
+        let mut a5 = A5::new(KEY, FRAME);
+        for _ in 0..50 {
+            let moved = a5.step();
+            assert!(moved.iter().filter(|m| **m).count() >= 2);
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let mut a5 = A5::new(KEY, FRAME);
+        let lines = a5.trace(3);
+        assert_eq!(lines.len(), 5);
+        assert_eq!(a5, { let mut b = A5::new(KEY, FRAME); (0..3).for_each(|_| { b.step(); }); b });
+        assert!(lines[1].contains(&A5::new(KEY, FRAME).registers[0].describe()));
+    }
+
+    #[test]
+    fn test_cipher() {
+        let c = (DESCRIPTOR.build)().set_key(&KeyValue::Framed(0x1223456789ABCDEF, 0x134)).unwrap();
+        let ct = c.encrypt("attack at dawn").unwrap();
+        assert_eq!(c.decrypt(&ct), Ok("attack at dawn".to_string()));
+        assert_eq!(c.decrypt("00"), Ok("S".to_string()));
+        assert_eq!(c.describe(), "0x1223456789abcdef frame 0x134");
+        assert!(c.set_key(&KeyValue::Framed(0, 1 << 22)).is_err());
+        assert_eq!(c.set_option("steps", "4096").unwrap().options(), vec![("steps", "4096".to_string())]);
+        assert!(matches!(c.set_option("steps", "4097"), Err(CryptoError::InvalidOption { .. })));
+    }
+
+}
@@ -0,0 +1,158 @@
+// Affine cipher: x -> a*x + b
+
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::{Key, Cipher};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::policy::Policy;
+use crate::registry::CipherDescriptor;
+use crate::tools::Zmod;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "affine",
+    aliases: &[],
+    key_type: "pair",
+    description: "multiply every letter by a and add b (a coprime with the alphabet size)",
+    build: || Box::new(Affine { key: (1, 0), message: "".to_string(), alphabet: Alphabet::default(), policy: Policy::default() }),
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Affine {
+    pub key: (i64, i64),
+    pub message: String,
+    pub alphabet: Alphabet,
+    pub policy: Policy
+}
+
+fn check_key(key: (i64, i64), alphabet: &Alphabet) -> Result<Zmod, CryptoError> {
+    Zmod::new(key.0, alphabet.len() as i64).inverse().ok_or(CryptoError::NotInvertible {
+        key: key.describe(),
+        modulus: alphabet.len() as i64
+    })
+}
+
+impl Affine {
+    fn zkey(&self) -> (Zmod, Zmod) {
+        let m = self.alphabet.len() as i64;
+        (Zmod::new(self.key.0, m), Zmod::new(self.key.1, m))
+    }
+}
+
+impl Cipher for Affine {
+    fn name(&self) -> &'static str {
+        "affine"
+    }
+
+    fn title(&self) -> &'static str {
+        "Affine"
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        let (a, b) = self.zkey();
+        self.policy.apply(msg, &self.alphabet, |_, x| a * x + b)
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        let (_, b) = self.zkey();
+        let inverse = check_key(self.key, &self.alphabet)?;
+        self.policy.apply(ct, &self.alphabet, |_, y| inverse * (y - b))
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let value = <(i64, i64)>::from_key(self.name(), key)?;
+        check_key(value, &self.alphabet)?;
+        Ok(Box::new(Affine {
+            key: value,
+            ..self.clone()
+        }))
+    }
+
+    fn describe(&self) -> String {
+        self.key.describe()
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Affine {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        Some(&self.alphabet)
+    }
+
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        check_key(self.key, alphabet)?;
+        Ok(Box::new(Affine {
+            alphabet: alphabet.clone(),
+            ..self.clone()
+        }))
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        Box::new(Affine {
+            policy,
+            ..self.clone()
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::gcd;
+
+    fn affine(a: i64, b: i64, message: &str) -> Affine {
+        Affine { key: (a, b), message: message.to_string(), alphabet: Alphabet::default(), policy: Policy::default() }
+    }
+
+    #[test]
+    fn test_affine_encrypt() {
+        assert_eq!(
+            affine(5, 8, "affine cipher").encrypt2(),
+            Ok("ihhwvc swfrcp".to_string()));
+    }
+
+    #[test]
+    fn test_affine_decrypt() {
+        assert_eq!(
+            affine(5, 8, "ihhwvc swfrcp").decrypt2(),
+            Ok("affine cipher".to_string()));
+    }
+
+    #[test]
+    fn test_affine_every_key() {
+        let m = "thequickbrownfoxjumpsoverthelazydog";
+        for a in (-30..30_i64).filter(|a| gcd(*a, 26) == 1) {
+            for b in -30..30 {
+                let c = affine(a, b, m);
+                assert_eq!(c.decrypt(&c.encrypt2().unwrap()), Ok(m.to_string()), "key {},{}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_affine_invalid_key() {
+        let c = affine(1, 0, "");
+        assert_eq!(
+            c.set_key(&KeyValue::Pair(13, 2)).err(),
+            Some(CryptoError::NotInvertible { key: "13,2".to_string(), modulus: 26 }));
+        assert!(c.set_key(&KeyValue::Integer(3)).is_err());
+        let c = c.set_key(&KeyValue::Pair(3, 2)).unwrap();
+        assert!(c.set_alphabet(&Alphabet::custom("abcdef").unwrap()).is_err());
+    }
+
+}
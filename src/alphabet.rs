@@ -0,0 +1,144 @@
+// Alphabets the classical ciphers work on
+
+use std::collections::HashMap;
+use crate::error::CryptoError;
+use crate::tools::Zmod;
+
+const LOWERCASE: &str = "abcdefghijklmnopqrstuvwxyz";
+const UPPERCASE: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+const FRENCH_ACCENTS: &str = "àâæçéèêëîïôœùûüÿ";
+
+/// An ordered set of letters with constant time lookup in both directions.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Alphabet {
+    name: String,
+    letters: Vec<char>,
+    index: HashMap<char, usize>
+}
+
+impl Alphabet {
+    fn build(name: &str, letters: &str) -> Alphabet {
+        let letters = letters.chars().collect::<Vec<char>>();
+        let index = letters.iter().enumerate().map(|(i, c)| (*c, i)).collect();
+        Alphabet { name: name.to_string(), letters, index }
+    }
+
+    pub fn lowercase() -> Alphabet {
+        Alphabet::build("lowercase", LOWERCASE)
+    }
+
+    pub fn mixed() -> Alphabet {
+        Alphabet::build("mixed", &format!("{}{}", LOWERCASE, UPPERCASE))
+    }
+
+    pub fn alphanumeric() -> Alphabet {
+        Alphabet::build("alphanumeric", &format!("{}{}{}", LOWERCASE, UPPERCASE, DIGITS))
+    }
+
+    pub fn printable() -> Alphabet {
+        Alphabet::build("printable", &(' '..='~').collect::<String>())
+    }
+
+    pub fn french() -> Alphabet {
+        Alphabet::build("french", &format!("{}{}", LOWERCASE, FRENCH_ACCENTS))
+    }
+
+    /// An alphabet made of the given letters, in order and without repetition.
+    pub fn custom(letters: &str) -> Result<Alphabet, CryptoError> {
+        let alphabet = Alphabet::build("custom", letters);
+        if alphabet.letters.len() < 2 || alphabet.index.len() != alphabet.letters.len() {
+            return Err(CryptoError::InvalidAlphabet(letters.to_string()));
+        }
+        Ok(alphabet)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        vec!["lowercase", "mixed", "alphanumeric", "printable", "french"]
+    }
+
+    pub fn named(name: &str) -> Option<Alphabet> {
+        match name {
+            "lowercase" => Some(Alphabet::lowercase()),
+            "mixed" => Some(Alphabet::mixed()),
+            "alphanumeric" => Some(Alphabet::alphanumeric()),
+            "printable" => Some(Alphabet::printable()),
+            "french" => Some(Alphabet::french()),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.letters.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.letters.is_empty()
+    }
+
+    pub fn letters(&self) -> &[char] {
+        &self.letters
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.index.contains_key(&c)
+    }
+
+    /// Position of `c` in the alphabet.
+    pub fn letter_to_number(&self, c: char) -> Result<Zmod, CryptoError> {
+        match self.index.get(&c) {
+            Some(i) => Ok(Zmod::new(*i as i64, self.len() as i64)),
+            None => Err(CryptoError::InvalidCharacter(c))
+        }
+    }
+
+    /// Letter at position `n` in the alphabet.
+    pub fn number_to_letter(&self, n: Zmod) -> char {
+        self.letters[n.value() as usize]
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} ({} letters)", self.name, self.len())
+    }
+}
+
+impl Default for Alphabet {
+    fn default() -> Alphabet {
+        Alphabet::lowercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let a = Alphabet::lowercase();
+        assert_eq!(a.letter_to_number('a').map(|n| n.value()), Ok(0));
+        assert_eq!(a.letter_to_number('z').map(|n| n.value()), Ok(25));
+        assert_eq!(a.number_to_letter(Zmod::new(27, 26)), 'b');
+    }
+
+    #[test]
+    fn test_sizes() {
+        assert_eq!(Alphabet::mixed().len(), 52);
+        assert_eq!(Alphabet::alphanumeric().len(), 62);
+        assert_eq!(Alphabet::printable().len(), 95);
+        assert_eq!(Alphabet::french().len(), 42);
+        assert_eq!(Alphabet::french().letter_to_number('é').map(|n| n.value()), Ok(30));
+    }
+
+    #[test]
+    fn test_custom() {
+        assert_eq!(Alphabet::custom("xyz").unwrap().len(), 3);
+        assert_eq!(
+            Alphabet::custom("xyzx"),
+            Err(CryptoError::InvalidAlphabet("xyzx".to_string())));
+    }
+
+}
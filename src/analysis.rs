@@ -0,0 +1,129 @@
+// Frequency analysis of a text
+
+use std::collections::HashMap;
+use crate::alphabet::Alphabet;
+use crate::language::Language;
+
+const TOP: usize = 10;
+const BAR_WIDTH: usize = 30;
+
+/// The letters of `text` that belong to `alphabet`, folding the case when the
+/// alphabet only has one.
+pub fn letters(text: &str, alphabet: &Alphabet) -> Vec<char> {
+    text.chars()
+        .filter_map(|c| if alphabet.contains(c) {
+            Some(c)
+        } else {
+            c.to_lowercase().next().filter(|l| alphabet.contains(*l))
+        })
+        .collect()
+}
+
+/// Occurrences of every sequence of `n` consecutive letters, the most
+/// frequent first.
+pub fn ngrams(letters: &[char], n: usize) -> Vec<(String, usize)> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for window in letters.windows(n) {
+        *counts.entry(window.iter().collect()).or_insert(0) += 1;
+    }
+    let mut res = counts.into_iter().collect::<Vec<(String, usize)>>();
+    res.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    res
+}
+
+/// Probability that two letters picked at random in the text are equal.
+pub fn index_of_coincidence(letters: &[char]) -> f64 {
+    let n = letters.len() as f64;
+    if n < 2.0 {
+        return 0.0;
+    }
+    let same = ngrams(letters, 1).iter()
+        .map(|(_, c)| (c * (c - 1)) as f64)
+        .sum::<f64>();
+    same / (n * (n - 1.0))
+}
+
+fn bar(observed: f64, expected: f64) -> String {
+    let observed = ((observed * BAR_WIDTH as f64 / 20.0).round() as usize).min(BAR_WIDTH);
+    let expected = (expected * BAR_WIDTH as f64 / 20.0).round() as usize;
+    (0..BAR_WIDTH)
+        .map(|i| if i == expected { '|' } else if i < observed { '#' } else { ' ' })
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
+
+fn top(title: &str, ngrams: &[(String, usize)], total: usize) -> String {
+    let rows = ngrams.iter().take(TOP)
+        .map(|(g, c)| format!("  {} {:>4} {:>6.2}%", g, c, 100.0 * *c as f64 / total.max(1) as f64))
+        .collect::<Vec<String>>();
+    format!("{}:\n{}", title, rows.join("\n"))
+}
+
+/// Letter, bigram and trigram frequencies, index of coincidence and a bar
+/// chart of the letters against `language` (`|` marks the expected value).
+pub fn report(text: &str, alphabet: &Alphabet, language: Language) -> String {
+    let letters = letters(text, alphabet);
+    let total = letters.len();
+    if total == 0 {
+        return "no letters to analyze".to_string();
+    }
+    let counts = ngrams(&letters, 1).into_iter().collect::<HashMap<String, usize>>();
+    let chart = alphabet.letters().iter()
+        .map(|c| {
+            let count = counts.get(&c.to_string()).copied().unwrap_or(0);
+            let observed = 100.0 * count as f64 / total as f64;
+            let expected = match c {
+                'a'..='z' => language.frequencies()[(*c as u8 - b'a') as usize],
+                _ => 0.0
+            };
+            format!("  {} {:>4} {:>6.2}% {:>6.2}% {}", c, count, observed, expected, bar(observed, expected))
+        })
+        .collect::<Vec<String>>();
+    let expected_ioc = language.frequencies().iter().map(|f| (f / 100.0).powi(2)).sum::<f64>();
+    [
+        format!("letters: {}", total),
+        format!("index of coincidence: {:.4} ({} {:.4}, random {:.4})",
+            index_of_coincidence(&letters), language.name(), expected_ioc, 1.0 / alphabet.len() as f64),
+        format!("letters (observed, {}):\n{}", language.name(), chart.join("\n")),
+        top("bigrams", &ngrams(&letters, 2), total.saturating_sub(1)),
+        top("trigrams", &ngrams(&letters, 3), total.saturating_sub(2)),
+    ].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ngrams() {
+        let l = letters("Hello, hello!", &Alphabet::lowercase());
+        assert_eq!(l.len(), 10);
+        assert_eq!(ngrams(&l, 1)[0], ("l".to_string(), 4));
+        assert_eq!(ngrams(&l, 2)[0], ("el".to_string(), 2));
+        assert_eq!(ngrams(&l, 3).len(), 5);
+    }
+
+    #[test]
+    fn test_index_of_coincidence() {
+        assert_eq!(index_of_coincidence(&['a', 'a', 'a']), 1.0);
+        assert_eq!(index_of_coincidence(&['a', 'b']), 0.0);
+        assert_eq!(index_of_coincidence(&['a', 'b', 'a', 'b']), 1.0 / 3.0);
+    }
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(10.0, 5.0), "########|######");
+        assert_eq!(bar(0.0, 2.0), "   |");
+        assert_eq!(bar(50.0, 25.0).len(), BAR_WIDTH);
+    }
+
+    #[test]
+    fn test_report() {
+        let r = report("attack at dawn", &Alphabet::lowercase(), Language::English);
+        assert!(r.starts_with("letters: 12\n"));
+        assert!(r.contains("  a    4  33.33%   8.17% "));
+        assert_eq!(report("123", &Alphabet::lowercase(), Language::English), "no letters to analyze");
+    }
+
+}
@@ -1,126 +1,311 @@
 #![allow(dead_code, unused_variables, unused_imports, unreachable_code)]
 
-use crate::cesar::Cesar;
-use crate::vigenere::Vigenere;
+use crate::alphabet::Alphabet;
+use crate::error::CryptoError;
 use crate::parser::commands::KeyValue;
+use crate::policy::Policy;
+use crate::registry;
 
-pub trait Key: std::ops::Add + Sized { }
+/// A key type that can be read from a parsed [`KeyValue`].
+pub trait Key: Sized {
+    fn parse(value: &KeyValue) -> Option<Self>;
+    fn describe(&self) -> String;
 
-pub trait Cipher { }
+    fn from_key(algo: &str, value: &KeyValue) -> Result<Self, CryptoError> {
+        Self::parse(value).ok_or_else(|| CryptoError::UnsupportedKey {
+            algo: algo.to_string(),
+            key: value.describe()
+        })
+    }
+}
+
+impl Key for i64 {
+    fn parse(value: &KeyValue) -> Option<i64> {
+        match value {
+            KeyValue::Integer(i) => Some(*i),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Key for (i64, i64) {
+    fn parse(value: &KeyValue) -> Option<(i64, i64)> {
+        match value {
+            KeyValue::Pair(a, b) => Some((*a, *b)),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{},{}", self.0, self.1)
+    }
+}
+
+impl Key for String {
+    fn parse(value: &KeyValue) -> Option<String> {
+        match value {
+            KeyValue::String(s) => Some(s.clone()),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// A keyed cipher holding its current message.
+pub trait Cipher: std::fmt::Debug {
+    fn name(&self) -> &'static str;
+    fn title(&self) -> &'static str;
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError>;
+    fn decrypt(&self, msg: &str) -> Result<String, CryptoError>;
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError>;
+    fn describe(&self) -> String;
+    fn get_message(&self) -> String;
+    fn set_message(&self, message: &str) -> Box<dyn Cipher>;
+    fn clone_box(&self) -> Box<dyn Cipher>;
+
+    /// The alphabet of the letters the cipher works on; the stream ciphers
+    /// work on bytes and have none.
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        None
+    }
+
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        Ok(self.clone_box())
+    }
+
+    fn get_policy(&self) -> Policy {
+        Policy::default()
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        self.clone_box()
+    }
+
+    /// The key as students should see it; ciphers with a table override it.
+    fn show_key(&self) -> String {
+        self.describe()
+    }
+
+    /// Options specific to the cipher, set with `set <option> <value>`.
+    fn set_option(&self, option: &str, value: &str) -> Result<Box<dyn Cipher>, CryptoError> {
+        Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+    }
+
+    /// Set several options at once, the cipher checking only the result.
+    fn set_options(&self, options: &[(String, String)]) -> Result<Box<dyn Cipher>, CryptoError> {
+        options.iter().try_fold(self.clone_box(), |c, (o, v)| c.set_option(o, v))
+    }
+
+    /// The options of [`Cipher::set_option`] with their current values.
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![]
+    }
+
+    /// Decrypt `ct` with every key from `from` to `to`, for the ciphers whose
+    /// key space is small enough.
+    fn brute_force(&self, ct: &str, from: i64, to: i64) -> Result<Vec<(String, String)>, CryptoError> {
+        Err(CryptoError::UnsupportedKey {
+            algo: self.name().to_string(),
+            key: KeyValue::Range(from, to).describe()
+        })
+    }
+
+    /// Recover the key and the plaintext from `ct` alone.
+    fn crack(&self, ct: &str) -> Result<String, CryptoError> {
+        Err(CryptoError::UnsupportedOperation { algo: self.name().to_string(), operation: "crack".to_string() })
+    }
+
+    /// Recover the key and the plaintext from `ct` knowing the start of the plaintext.
+    fn crack_known(&self, ct: &str, plain: &str) -> Result<String, CryptoError> {
+        Err(CryptoError::UnsupportedOperation { algo: self.name().to_string(), operation: "known-plaintext crack".to_string() })
+    }
 
-#[derive(PartialEq, Debug, Clone)]
+    /// The bits XORed with the message, for the stream ciphers.
+    fn keystream(&self) -> Result<Box<dyn Iterator<Item = u8>>, CryptoError> {
+        Err(CryptoError::UnsupportedOperation { algo: self.name().to_string(), operation: "keystream".to_string() })
+    }
+
+    fn encrypt2(&self) -> Result<String, CryptoError> {
+        self.encrypt(&self.get_message())
+    }
+
+    fn decrypt2(&self) -> Result<String, CryptoError> {
+        self.decrypt(&self.get_message())
+    }
+}
+
+impl Clone for Box<dyn Cipher> {
+    fn clone(&self) -> Box<dyn Cipher> {
+        self.clone_box()
+    }
+}
+
+/// The state of the session: the selected cipher, nothing, or the exit request.
+#[derive(Debug, Clone)]
 pub enum Algo {
-    Cesar(Cesar),
-    Vigenere(Vigenere),
+    Cipher(Box<dyn Cipher>),
     Empty,
     Exit,
 }
 
 impl Algo {
-    pub fn from(algo: Algo) -> Algo {
-        match algo {
-            Algo::Cesar(_) => Algo::Cesar(Cesar {key: 0, message: "".to_string()}),
-            _ => Algo::Empty
-        }
+    pub fn new(algo: &str) -> Result<Algo, CryptoError> {
+        registry::find(algo).map(|descriptor| Algo::Cipher((descriptor.build)()))
     }
 
-    pub fn new(algo: &str) -> Algo {
-        match algo {
-            "cesar" => Algo::Cesar(Cesar {key: 0, message: "".to_string()}),
-            "vigenere" => Algo::Vigenere(Vigenere {key: "".to_string(), message: "".to_string()}),
-            _ => Algo::Empty
+    fn cipher(&self) -> Result<&dyn Cipher, CryptoError> {
+        match self {
+            Algo::Cipher(c) => Ok(c.as_ref()),
+            _ => Err(CryptoError::NoAlgorithm)
         }
     }
 
-    pub fn encrypt(&self, msg: &str, key: KeyValue) -> String {
-       match (self, key.clone()) {
-           (Algo::Cesar(c), KeyValue::Integer(i)) => c.encrypt(msg, i),
-           (Algo::Vigenere(v), KeyValue::String(s)) => v.encrypt(msg, &s),
-           _ => format!("The algorithm {:?} don't work with a key {}. hint: try to change the key type", self, &key.describe())
-       } 
+    pub fn encrypt(&self, msg: &str, key: KeyValue) -> Result<String, CryptoError> {
+        self.cipher()?.set_key(&key)?.encrypt(msg)
     }
 
-    pub fn encrypt2(&self) -> String {
-       match self {
-           Algo::Cesar(c) => c.encrypt2(),
-           Algo::Vigenere(v) => v.encrypt2(),
-           _ => "The algorithm doesn't exist".to_string()
-       } 
+    pub fn encrypt2(&self) -> Result<String, CryptoError> {
+        self.cipher()?.encrypt2()
     }
 
-    pub fn decrypt(&self, msg: &str, key: KeyValue) -> String {
-       match (self, key.clone()) {
-           (Algo::Cesar(c), KeyValue::Integer(i)) => c.decrypt(msg, i),
-           (Algo::Vigenere(v), KeyValue::String(s)) => v.decrypt(msg, &s),
-           _ => format!("The algorithm {:?} don't work with a key {}. hint: try to change the key type", self, key.describe())
-       } 
+    pub fn decrypt(&self, msg: &str, key: KeyValue) -> Result<String, CryptoError> {
+        match key {
+            KeyValue::Range(from, to) => self.brute_force(msg, from, to),
+            key => self.cipher()?.set_key(&key)?.decrypt(msg)
+        }
     }
 
-    pub fn decrypt2(&self) -> String {
-       match self {
-           Algo::Cesar(c) => c.decrypt2(),
-           Algo::Vigenere(v) => v.decrypt2(),
-           _ => "The algorithm doesn't exist".to_string()
-       } 
-    }
-
-    pub fn set_key(&self, key: KeyValue) -> Algo {
-        match (self, key.clone()) {
-            (Algo::Cesar(c), KeyValue::Integer(i)) => Algo::Cesar(c.set_key(i)),
-            (Algo::Vigenere(v), KeyValue::String(s)) => Algo::Vigenere(v.set_key(&s)),
-            (Algo::Empty, _) => Algo::Empty,
-            (Algo::Exit, _) => Algo::Exit,
-            (Algo::Cesar(c), _) => Algo::Cesar(c.clone()),
-            (Algo::Vigenere(v), _) => Algo::Vigenere(v.clone()),
+    /// Table of the candidates of a brute force attack, the most plausible
+    /// plaintext first. The range goes up, from its smallest key.
+    pub fn brute_force(&self, msg: &str, from: i64, to: i64) -> Result<String, CryptoError> {
+        let cipher = self.cipher()?;
+        if from > to {
+            let range = KeyValue::Range(from, to).syntax();
+            return Err(CryptoError::InvalidKey(format!("the range {} is empty, the smallest key comes first", range)));
         }
+        let language = cipher.get_policy().language;
+        let mut candidates = cipher.brute_force(msg, from, to)?.into_iter()
+            .map(|(key, plain)| (language.score(&plain), key, plain))
+            .collect::<Vec<(f64, String, String)>>();
+        candidates.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let rows = candidates.iter().enumerate()
+            .map(|(i, (score, key, plain))| format!("{:>4} {:>6} {:>9.2}  {}", i + 1, key, score, plain))
+            .collect::<Vec<String>>();
+        Ok(format!("rank    key     score  plaintext ({})\n{}", language.name(), rows.join("\n")))
+    }
+
+    pub fn decrypt2(&self) -> Result<String, CryptoError> {
+        self.cipher()?.decrypt2()
+    }
+
+    pub fn crack(&self, msg: Option<String>, known: Option<String>) -> Result<String, CryptoError> {
+        let cipher = self.cipher()?;
+        let ct = msg.unwrap_or_else(|| cipher.get_message());
+        match known {
+            Some(plain) => cipher.crack_known(&ct, &plain),
+            None => cipher.crack(&ct)
+        }
+    }
+
+    pub fn set_key(&self, key: KeyValue) -> Result<Algo, CryptoError> {
+        Ok(Algo::Cipher(self.cipher()?.set_key(&key)?))
     }
 
-    pub fn get_key(&self) -> i8 {
+    pub fn get_key(&self) -> String {
         match self {
-            Algo::Cesar(c) => c.get_key(),
-            _ => 0
+            Algo::Cipher(c) => c.describe(),
+            _ => "[not defined]".to_string()
         }
     }
 
+    pub fn show_key(&self) -> Result<String, CryptoError> {
+        Ok(self.cipher()?.show_key())
+    }
+
     pub fn get_prompt(&self) -> String {
        match self {
-           Algo::Cesar(_) => ":[Cesar]:> ".to_string(),
-           Algo::Vigenere(_) => ":[Vigenere]:> ".to_string(),
+           Algo::Cipher(c) => format!(":[{}]:> ", c.title()),
            _ => ":[?]:> ".to_string()
-       } 
+       }
+    }
+
+    pub fn set_message(&self, message: &str) -> Result<Algo, CryptoError> {
+        Ok(Algo::Cipher(self.cipher()?.set_message(message)))
+    }
+
+    pub fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Algo, CryptoError> {
+        Ok(Algo::Cipher(self.cipher()?.set_alphabet(alphabet)?))
+    }
+
+    pub fn get_alphabet(&self) -> Option<Alphabet> {
+        match self {
+            Algo::Cipher(c) => c.get_alphabet().cloned(),
+            _ => None
+        }
+    }
+
+    pub fn set_option(&self, option: &str, value: &str) -> Result<Algo, CryptoError> {
+        let cipher = self.cipher()?;
+        if Policy::has_option(option) {
+            Ok(Algo::Cipher(cipher.set_policy(cipher.get_policy().set(option, value)?)))
+        } else {
+            Ok(Algo::Cipher(cipher.set_option(option, value)?))
+        }
+    }
+
+    pub fn set_options(&self, options: &[(String, String)]) -> Result<Algo, CryptoError> {
+        Ok(Algo::Cipher(self.cipher()?.set_options(options)?))
+    }
+
+    pub fn options(&self) -> Vec<(&'static str, String)> {
+        match self {
+            Algo::Cipher(c) => c.options(),
+            _ => vec![]
+        }
+    }
+
+    /// The first `n` bits of the keystream of the current cipher.
+    pub fn keystream(&self, n: usize) -> Result<Vec<u8>, CryptoError> {
+        Ok(self.cipher()?.keystream()?.take(n).collect())
     }
 
-    pub fn set_message(&self, message: &str) -> Algo {
+    pub fn get_policy(&self) -> Policy {
         match self {
-            Algo::Cesar(c) => Algo::Cesar(c.set_message(message)),
-            _ => Algo::Empty
+            Algo::Cipher(c) => c.get_policy(),
+            _ => Policy::default()
         }
     }
 
     pub fn get_message(&self) -> String {
         match self {
-            Algo::Cesar(c) => c.get_message(),
+            Algo::Cipher(c) => c.get_message(),
             _ => "".to_string()
         }
     }
 
     pub fn get_algo(&self) -> String {
         match self {
-            Algo::Cesar(_) => "cesar".to_string(),
-            Algo::Vigenere(_) => "vigenere".to_string(),
-            Algo::Empty => "[not defined]".to_string(),
-            Algo::Exit => "[not defined]".to_string()
+            Algo::Cipher(c) => c.name().to_string(),
+            _ => "[not defined]".to_string()
         }
     }
 
-    pub fn get_status(&self) -> String {
+    pub fn get_title(&self) -> String {
         match self {
-            algo => format!("algo: {} | key: {} | message: {}", algo.get_algo(), algo.get_key(), algo.get_message())
+            Algo::Cipher(c) => c.title().to_string(),
+            _ => "?".to_string()
         }
     }
 
     pub fn list_algo() -> String {
-        "??".to_string()
+        registry::names().join(", ")
     }
 
 }
@@ -1,100 +1,207 @@
 #![allow(dead_code, unused_variables, unused_imports, unreachable_code)]
 
+use crate::alphabet::Alphabet;
 use crate::base_cryptography::{Key, Cipher};
-use crate::tools::{get_alphabet, letter_to_number, number_to_letter};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::policy::Policy;
+use crate::registry::CipherDescriptor;
+use crate::tools::Zmod;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "cesar",
+    aliases: &["caesar", "shift"],
+    key_type: "integer",
+    description: "shift every letter by a fixed amount",
+    build: || Box::new(Cesar { key: 0, message: "".to_string(), alphabet: Alphabet::default(), policy: Policy::default() }),
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Cesar {
-    pub key: i8,
-    pub message: String
+    pub key: i64,
+    pub message: String,
+    pub alphabet: Alphabet,
+    pub policy: Policy
 }
 
-impl Key for i8 { }
+fn compute(key: Zmod) -> impl Fn(usize, Zmod) -> Zmod {
+   move |_, x| x + key
+}
 
-impl Cipher for String { }
+impl Cesar {
+    fn shift(&self, msg: &str, key: Zmod) -> Result<String, CryptoError> {
+        self.policy.apply(msg, &self.alphabet, compute(key))
+    }
 
-fn compute<N>(key: i8) -> impl Fn(i8) -> i8 {
-   move |x| (x + key) % 26
+    fn zkey(&self) -> Zmod {
+        Zmod::new(self.key, self.alphabet.len() as i64)
+    }
 }
 
-impl Cesar {
-    pub fn encrypt(&self, msg: &str, key: i8) -> String {
-        msg.chars()
-            .map(letter_to_number)
-            .map(compute::<i8>(key))
-            .map(number_to_letter)
-            .collect::<String>()
+impl Cipher for Cesar {
+    fn name(&self) -> &'static str {
+        "cesar"
     }
 
-    pub fn encrypt2(&self) -> String {
-        self.message.chars()
-            .map(letter_to_number)
-            .map(compute::<i8>(self.key))
-            .map(number_to_letter)
-            .collect::<String>()
+    fn title(&self) -> &'static str {
+        "Cesar"
     }
 
-    pub fn decrypt(&self, ct: &str, key: i8) -> String {
-        self.encrypt(ct, -key)
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        self.shift(msg, self.zkey())
     }
 
-    pub fn decrypt2(&self) -> String {
-        self.encrypt(&self.message, -self.key)
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        self.shift(ct, -self.zkey())
     }
 
-    pub fn set_key(&self, value: i8) -> Cesar {
-        Cesar {
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        i64::from_key(self.name(), key).map(|value| Box::new(Cesar {
             key: value,
-            message: self.message.clone()
-        }
+            ..self.clone()
+        }) as Box<dyn Cipher>)
     }
 
-    pub fn get_key(&self) -> i8 {
-        self.key
+    fn describe(&self) -> String {
+        self.key.describe()
     }
 
-    pub fn get_message(&self) -> String {
+    fn get_message(&self) -> String {
         self.message.clone()
     }
 
-    pub fn set_message(&self, message: &str) -> Cesar {
-        Cesar {
-            key: self.key,
-            message: message.to_string()
-        }
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Cesar {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        Some(&self.alphabet)
     }
 
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        Ok(Box::new(Cesar {
+            alphabet: alphabet.clone(),
+            ..self.clone()
+        }))
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        Box::new(Cesar {
+            policy,
+            ..self.clone()
+        })
+    }
+
+    fn brute_force(&self, ct: &str, from: i64, to: i64) -> Result<Vec<(String, String)>, CryptoError> {
+        let size = self.alphabet.len() as i64;
+        (from..=to.min(from.saturating_add(size - 1)))
+            .map(|key| self.shift(ct, -Zmod::new(key, size)).map(|plain| (key.to_string(), plain)))
+            .collect()
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn cesar(key: i64, message: &str) -> Cesar {
+        Cesar { key, message: message.to_string(), alphabet: Alphabet::default(), policy: Policy::default() }
+    }
+
     #[test]
     fn test(){
-        assert_eq!( letter_to_number('a'), 0);
-        assert_eq!( letter_to_number('z'), 25);
+        assert_eq!( Alphabet::default().letter_to_number('a').map(|n| n.value()), Ok(0));
+        assert_eq!( Alphabet::default().letter_to_number('z').map(|n| n.value()), Ok(25));
+        assert_eq!( Alphabet::default().letter_to_number('A'), Err(CryptoError::InvalidCharacter('A')));
     }
 
     #[test]
     fn test_number_to_letter() {
         assert_eq!(
-            number_to_letter(0),
+            Alphabet::default().number_to_letter(Zmod::new(0, 26)),
             'a');
     }
 
     #[test]
     fn test_encrypt() {
         assert_eq!(
-            Cesar{ key: 2, message: "a".to_string() }.encrypt2(),
-            "c"); 
+            cesar(2, "a").encrypt2(),
+            Ok("c".to_string()));
     }
 
     #[test]
     fn test_decrypt() {
         assert_eq!(
-            Cesar{ key: 2, message: "c".to_string() }.decrypt2(),
-            "a"); 
+            cesar(2, "c").decrypt2(),
+            Ok("a".to_string()));
+    }
+
+    #[test]
+    fn test_decrypt_encrypt_every_key() {
+        let m = "thequickbrownfoxjumpsoverthelazydog";
+        for key in (-300..=300).chain([i64::MIN, i64::MAX]) {
+            let c = cesar(key, "");
+            assert_eq!(
+                c.decrypt(&c.encrypt(m).unwrap()),
+                Ok(m.to_string()), "key {}", key);
+        }
+    }
+
+    #[test]
+    fn test_large_key() {
+        assert_eq!(
+            cesar(300, "a").encrypt2(),
+            Ok("o".to_string()));
+        assert_eq!(
+            cesar(-1, "a").encrypt2(),
+            Ok("z".to_string()));
+    }
+
+    #[test]
+    fn test_other_alphabet() {
+        let c = Cesar { key: 3, message: "Zz9".to_string(), alphabet: Alphabet::alphanumeric(), policy: Policy::default() };
+        assert_eq!(c.encrypt2(), Ok("2Cc".to_string()));
+        assert_eq!(c.decrypt(&c.encrypt2().unwrap()), Ok("Zz9".to_string()));
+    }
+
+    #[test]
+    fn test_preserve_text() {
+        assert_eq!(
+            cesar(3, "Hello, World! 42").encrypt2(),
+            Ok("Khoor, Zruog! 42".to_string()));
+        assert_eq!(
+            cesar(3, "Khoor, Zruog! 42").decrypt2(),
+            Ok("Hello, World! 42".to_string()));
+    }
+
+    #[test]
+    fn test_brute_force() {
+        let candidates = cesar(0, "").brute_force("Khoor", 0, 1000).unwrap();
+        assert_eq!(candidates.len(), 26);
+        assert_eq!(candidates[3], ("3".to_string(), "Hello".to_string()));
+        assert_eq!(cesar(0, "").brute_force("Khoor", 2, 4).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_set_key() {
+        let c = cesar(0, "a");
+        assert_eq!(
+            c.set_key(&KeyValue::Integer(3)).unwrap().encrypt("a"),
+            Ok("d".to_string()));
+        assert_eq!(
+            c.set_key(&KeyValue::String("b".to_string())).err(),
+            Some(CryptoError::UnsupportedKey { algo: "cesar".to_string(), key: "\"b\" [string]".to_string() }));
     }
 
 }
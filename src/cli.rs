@@ -0,0 +1,207 @@
+// Command line mode: the ciphers without the REPL, for shell pipelines
+
+use std::io::{Read, Write};
+use cryptography::alphabet::Alphabet;
+use cryptography::base_cryptography::Algo;
+use cryptography::error::CryptoError;
+use cryptography::parser::commands::{KeyValue, OnError};
+use cryptography::parser::parse_key_value_text;
+use cryptography::script;
+use cryptography::session::Session;
+
+pub const USAGE: &str = "usage:
+    cryptography                          start the interactive session
+    cryptography encrypt --algo <name> --key <key> [--alphabet <name>] [--in <file>] [--out <file>]
+    cryptography decrypt --algo <name> --key <key> [--alphabet <name>] [--in <file>] [--out <file>]
+    cryptography run [script] [--continue]  run the commands of a script
+    cryptography --script <script> [--continue]  run a script, showing each command with its result
+standard input and output are used when no file is given; --continue goes on after an error";
+
+/// Arguments of `encrypt` and `decrypt`.
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Options {
+    pub algo: String,
+    pub key: String,
+    pub alphabet: Option<String>,
+    pub input: Option<String>,
+    pub output: Option<String>,
+}
+
+/// What the program was asked to do.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Mode {
+    Interactive,
+    Encrypt(Options),
+    Decrypt(Options),
+    /// Run a script, from the standard input without a path.
+    Script { path: Option<String>, echo: bool, on_error: OnError },
+}
+
+fn usage(reason: &str) -> CryptoError {
+    CryptoError::Usage(reason.to_string())
+}
+
+fn parse_options(args: &[String]) -> Result<Options, CryptoError> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| usage(&format!("{} needs a value", flag)))?.clone();
+        match flag.as_str() {
+            "--algo" => options.algo = value,
+            "--key" => options.key = value,
+            "--alphabet" => options.alphabet = Some(value),
+            "--in" => options.input = Some(value),
+            "--out" => options.output = Some(value),
+            _ => return Err(usage(&format!("unknown option {}", flag)))
+        }
+    }
+    if options.algo.is_empty() {
+        return Err(usage("--algo is required"));
+    }
+    if options.key.is_empty() {
+        return Err(usage("--key is required"));
+    }
+    Ok(options)
+}
+
+fn parse_script(args: &[String], echo: bool) -> Result<Mode, CryptoError> {
+    let (on_error, args) = match args.split_last() {
+        Some((last, rest)) if last == "--continue" => (OnError::Continue, rest),
+        _ => (OnError::Stop, args)
+    };
+    match args {
+        [] if !echo => Ok(Mode::Script { path: None, echo, on_error }),
+        [path] => Ok(Mode::Script { path: Some(path.clone()), echo, on_error }),
+        _ => Err(usage("expected one script"))
+    }
+}
+
+/// Read the arguments, without the name of the program.
+pub fn parse_args(args: &[String]) -> Result<Mode, CryptoError> {
+    match args.split_first() {
+        None => Ok(Mode::Interactive),
+        Some((command, rest)) => match (command.as_str(), rest) {
+            ("encrypt", rest) => Ok(Mode::Encrypt(parse_options(rest)?)),
+            ("decrypt", rest) => Ok(Mode::Decrypt(parse_options(rest)?)),
+            ("run", rest) => parse_script(rest, false),
+            ("--script", rest) => parse_script(rest, true),
+            (command, _) => Err(usage(&format!("unknown command {}", command)))
+        }
+    }
+}
+
+/// A key as in the REPL; a word that isn't one is taken as a text key.
+fn key(text: &str) -> KeyValue {
+    parse_key_value_text(text).unwrap_or_else(|_| KeyValue::String(text.to_string()))
+}
+
+/// Encrypt or decrypt `text` as asked by `options`.
+pub fn convert(options: &Options, text: &str, encrypt: bool) -> Result<String, CryptoError> {
+    let mut algo = Algo::new(&options.algo)?;
+    if let Some(name) = &options.alphabet {
+        let alphabet = Alphabet::named(name).ok_or_else(|| usage(&format!("unknown alphabet {}", name)))?;
+        algo = algo.set_alphabet(&alphabet)?;
+    }
+    if encrypt {
+        algo.encrypt(text, key(&options.key))
+    } else {
+        algo.decrypt(text, key(&options.key))
+    }
+}
+
+fn io(path: &str, e: std::io::Error) -> CryptoError {
+    CryptoError::Io { path: path.to_string(), reason: e.to_string() }
+}
+
+fn read(path: &Option<String>) -> Result<String, CryptoError> {
+    match path {
+        Some(p) => std::fs::read_to_string(p).map_err(|e| io(p, e)),
+        None => {
+            let mut text = String::new();
+            std::io::stdin().read_to_string(&mut text).map_err(|e| io("standard input", e))?;
+            Ok(text)
+        }
+    }
+}
+
+fn write(path: &Option<String>, text: &str) -> Result<(), CryptoError> {
+    let text = if text.ends_with('\n') { text.to_string() } else { format!("{}\n", text) };
+    match path {
+        Some(p) => std::fs::write(p, text).map_err(|e| io(p, e)),
+        None => std::io::stdout().write_all(text.as_bytes()).map_err(|e| io("standard output", e))
+    }
+}
+
+/// Run a non interactive mode.
+pub fn run(mode: &Mode) -> Result<(), CryptoError> {
+    match mode {
+        Mode::Interactive => Ok(()),
+        Mode::Encrypt(options) => write(&options.output, &convert(options, &read(&options.input)?, true)?),
+        Mode::Decrypt(options) => write(&options.output, &convert(options, &read(&options.input)?, false)?),
+        Mode::Script { path, echo, on_error } => {
+            let session = Session { sources: path.iter().map(|p| script::canonical(p)).collect(), ..Session::new() };
+            let (_, errors) = script::execute(&read(path)?, &session, *echo, *on_error, &mut std::io::stdout(), &mut std::io::stderr());
+            match errors.len() {
+                0 => Ok(()),
+                n => Err(CryptoError::Failed(n))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&[]), Ok(Mode::Interactive));
+        assert_eq!(
+            parse_args(&args("encrypt --algo vigenere --key lemon --in plain.txt --out cipher.txt")),
+            Ok(Mode::Encrypt(Options {
+                algo: "vigenere".to_string(),
+                key: "lemon".to_string(),
+                alphabet: None,
+                input: Some("plain.txt".to_string()),
+                output: Some("cipher.txt".to_string())
+            })));
+        assert_eq!(
+            parse_args(&args("run lab.crypt")),
+            Ok(Mode::Script { path: Some("lab.crypt".to_string()), echo: false, on_error: OnError::Stop }));
+        assert_eq!(
+            parse_args(&args("--script lab.crypt --continue")),
+            Ok(Mode::Script { path: Some("lab.crypt".to_string()), echo: true, on_error: OnError::Continue }));
+        assert!(parse_args(&args("--script")).is_err());
+        assert!(parse_args(&args("encrypt --algo cesar")).is_err());
+        assert!(parse_args(&args("encrypt --algo cesar --key")).is_err());
+        assert!(parse_args(&args("frobnicate")).is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        let options = parse_options(&args("--algo vigenere --key lemon")).unwrap();
+        let ct = convert(&options, "attack at dawn\n", true).unwrap();
+        assert_eq!(ct, "lxfopv ef rnhr\n");
+        assert_eq!(convert(&options, &ct, false), Ok("attack at dawn\n".to_string()));
+        let options = parse_options(&args("--algo cesar --key 3 --alphabet mixed")).unwrap();
+        assert_eq!(convert(&options, "xyzA", true), Ok("ABCD".to_string()));
+    }
+
+    #[test]
+    fn test_run_sourced_failure() {
+        let dir = std::env::temp_dir();
+        let inner = dir.join(format!("cryptography-{}-run-inner.crypt", std::process::id()));
+        let outer = dir.join(format!("cryptography-{}-run-outer.crypt", std::process::id()));
+        std::fs::write(&inner, "select cesar\nkey \"abc\"\n").unwrap();
+        std::fs::write(&outer, format!("source {} continue\n", inner.display())).unwrap();
+        let res = run(&Mode::Script { path: Some(outer.display().to_string()), echo: false, on_error: OnError::Continue });
+        std::fs::remove_file(inner).unwrap();
+        std::fs::remove_file(outer).unwrap();
+        assert_eq!(res, Err(CryptoError::Failed(1)));
+    }
+
+}
@@ -0,0 +1,383 @@
+// Nonlinear generators combining several LFSRs, and their stream ciphers
+
+use crate::base_cryptography::{Key, Cipher};
+use crate::cryptanalysis::crack_geffe;
+use crate::error::CryptoError;
+use crate::lfsr::{Lfsr, Mode, Register};
+use crate::parser::commands::KeyValue;
+use crate::registry::CipherDescriptor;
+use crate::stream;
+
+pub const GEFFE: CipherDescriptor = CipherDescriptor {
+    name: "geffe",
+    aliases: &[],
+    key_type: "3 x seed [taps]",
+    description: "the second register chooses between the first and the third",
+    build: || Box::new(Combiner::new(Kind::Geffe)),
+};
+
+pub const SHRINKING: CipherDescriptor = CipherDescriptor {
+    name: "shrinking",
+    aliases: &[],
+    key_type: "2 x seed [taps]",
+    description: "the bits of the first register are kept when the second outputs 1",
+    build: || Box::new(Combiner::new(Kind::Shrinking)),
+};
+
+pub const SELF_SHRINKING: CipherDescriptor = CipherDescriptor {
+    name: "selfshrinking",
+    aliases: &["selfshrink"],
+    key_type: "seed [taps]",
+    description: "a register read by pairs, the second bit is kept when the first is 1",
+    build: || Box::new(Combiner::new(Kind::SelfShrinking)),
+};
+
+pub const ALTERNATING: CipherDescriptor = CipherDescriptor {
+    name: "alternating",
+    aliases: &["asg"],
+    key_type: "3 x seed [taps]",
+    description: "the first register clocks either the second or the third, which are XORed",
+    build: || Box::new(Combiner::new(Kind::Alternating)),
+};
+
+/// Output of the Geffe combining function.
+pub fn geffe(x1: u8, x2: u8, x3: u8) -> u8 {
+    (x1 & x2) ^ ((x2 ^ 1) & x3)
+}
+
+pub struct Geffe {
+    pub x1: Lfsr,
+    pub x2: Lfsr,
+    pub x3: Lfsr,
+}
+
+impl Iterator for Geffe {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        Some(geffe(self.x1.step(), self.x2.step(), self.x3.step()))
+    }
+}
+
+/// `next` only returns once `s` outputs a 1, which the cipher checks it does.
+pub struct Shrinking {
+    pub a: Lfsr,
+    pub s: Lfsr,
+}
+
+impl Iterator for Shrinking {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            let (a, s) = (self.a.step(), self.s.step());
+            if s == 1 {
+                return Some(a);
+            }
+        }
+    }
+}
+
+/// `next` only returns once `a` outputs a 1 at an even step, which the cipher
+/// checks it does.
+pub struct SelfShrinking {
+    pub a: Lfsr,
+}
+
+impl Iterator for SelfShrinking {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        loop {
+            let (s, a) = (self.a.step(), self.a.step());
+            if s == 1 {
+                return Some(a);
+            }
+        }
+    }
+}
+
+/// The register that is not clocked repeats its last bit.
+pub struct Alternating {
+    pub control: Lfsr,
+    pub r1: Lfsr,
+    pub r2: Lfsr,
+    last: (u8, u8),
+}
+
+impl Alternating {
+    pub fn new(control: Lfsr, r1: Lfsr, r2: Lfsr) -> Alternating {
+        Alternating { control, r1, r2, last: (0, 0) }
+    }
+}
+
+impl Iterator for Alternating {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        if self.control.step() == 1 {
+            self.last.0 = self.r1.step();
+        } else {
+            self.last.1 = self.r2.step();
+        }
+        Some(self.last.0 ^ self.last.1)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Kind {
+    Geffe,
+    Shrinking,
+    SelfShrinking,
+    Alternating,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Geffe => "geffe",
+            Kind::Shrinking => "shrinking",
+            Kind::SelfShrinking => "selfshrinking",
+            Kind::Alternating => "alternating",
+        }
+    }
+
+    fn title(&self) -> &'static str {
+        match self {
+            Kind::Geffe => "Geffe",
+            Kind::Shrinking => "Shrinking",
+            Kind::SelfShrinking => "Self-shrinking",
+            Kind::Alternating => "Alternating step",
+        }
+    }
+
+    /// Number of registers the generator is made of.
+    fn registers(&self) -> usize {
+        match self {
+            Kind::Geffe | Kind::Alternating => 3,
+            Kind::Shrinking => 2,
+            Kind::SelfShrinking => 1,
+        }
+    }
+}
+
+impl Key for Vec<Register> {
+    fn parse(value: &KeyValue) -> Option<Vec<Register>> {
+        let registers = match value {
+            KeyValue::Seeded(seed, taps) => vec![(*seed, taps.clone())],
+            KeyValue::Registers(r) => r.clone(),
+            _ => return None
+        };
+        registers.iter()
+            .map(|(seed, taps)| Register::parse(&KeyValue::Seeded(*seed, taps.clone())))
+            .collect()
+    }
+
+    fn describe(&self) -> String {
+        self.iter().map(|r| r.describe()).collect::<Vec<String>>().join(", ")
+    }
+}
+
+/// Stream cipher XORing the message with the output of a combining generator.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Combiner {
+    pub kind: Kind,
+    pub registers: Vec<Register>,
+    pub message: String
+}
+
+impl Combiner {
+    pub fn new(kind: Kind) -> Combiner {
+        Combiner {
+            kind,
+            registers: vec![],
+            message: "".to_string()
+        }
+    }
+
+    fn lfsrs(&self) -> Result<Vec<Lfsr>, CryptoError> {
+        if self.registers.is_empty() {
+            return Err(CryptoError::EmptyKey);
+        }
+        if self.registers.len() != self.kind.registers() {
+            return Err(CryptoError::InvalidKey(format!("{} needs {} registers, not {}",
+                self.kind.name(), self.kind.registers(), self.registers.len())));
+        }
+        let lfsrs = self.registers.iter()
+            .map(|(seed, taps)| Lfsr::from_taps(taps, *seed, Mode::Fibonacci))
+            .collect::<Result<Vec<Lfsr>, CryptoError>>()?;
+        // The connection polynomial has a term of the degree of the register,
+        // so its output, and the output at even steps which follows the same
+        // polynomial, is only zeros if its first `length` bits are.
+        let control = match self.kind {
+            Kind::Shrinking => Some(lfsrs[1].clone().take(lfsrs[1].length()).collect::<Vec<u8>>()),
+            Kind::SelfShrinking => Some(lfsrs[0].clone().step_by(2).take(lfsrs[0].length()).collect::<Vec<u8>>()),
+            _ => None
+        };
+        match control {
+            Some(bits) if !bits.contains(&1) =>
+                Err(CryptoError::InvalidKey("the control bits are all zeros, no bit would be output".to_string())),
+            _ => Ok(lfsrs)
+        }
+    }
+}
+
+impl Cipher for Combiner {
+    fn name(&self) -> &'static str {
+        self.kind.name()
+    }
+
+    fn title(&self) -> &'static str {
+        self.kind.title()
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        Ok(stream::encrypt(msg, self.keystream()?))
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        stream::decrypt(ct, self.keystream()?)
+    }
+
+    fn keystream(&self) -> Result<Box<dyn Iterator<Item = u8>>, CryptoError> {
+        let mut lfsrs = self.lfsrs()?.into_iter();
+        let mut next = || lfsrs.next().ok_or(CryptoError::EmptyKey);
+        Ok(match self.kind {
+            Kind::Geffe => Box::new(Geffe { x1: next()?, x2: next()?, x3: next()? }),
+            Kind::Shrinking => Box::new(Shrinking { a: next()?, s: next()? }),
+            Kind::SelfShrinking => Box::new(SelfShrinking { a: next()? }),
+            Kind::Alternating => Box::new(Alternating::new(next()?, next()?, next()?)),
+        })
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let registers = Vec::<Register>::from_key(self.name(), key)?;
+        let cipher = Combiner { registers, ..self.clone() };
+        cipher.lfsrs()?;
+        Ok(Box::new(cipher))
+    }
+
+    fn describe(&self) -> String {
+        self.registers.describe()
+    }
+
+    fn show_key(&self) -> String {
+        match self.lfsrs() {
+            Ok(lfsrs) => lfsrs.iter().enumerate()
+                .map(|(i, l)| format!("R{}: {} (seed {:#x})", i + 1, l.polynomial(), l.state()))
+                .collect::<Vec<String>>()
+                .join("\n"),
+            Err(e) => e.to_string()
+        }
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Combiner {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    /// Correlation attack on Geffe: only the taps of the key are used.
+    fn crack_known(&self, ct: &str, plain: &str) -> Result<String, CryptoError> {
+        if self.kind != Kind::Geffe {
+            return Err(CryptoError::UnsupportedOperation { algo: self.name().to_string(), operation: "known-plaintext crack".to_string() });
+        }
+        self.lfsrs()?;
+        let bits = stream::keystream(ct, plain)?;
+        let taps = self.registers.iter().map(|(_, t)| t.clone()).collect::<Vec<Vec<usize>>>();
+        let found = crack_geffe(&bits, &taps)?;
+        let registers = found.iter().zip(taps)
+            .map(|((seed, _), t)| (*seed, t))
+            .collect::<Vec<Register>>();
+        let lines = found.iter().enumerate()
+            .map(|(i, (seed, agreement))| format!("R{}: seed {:#x} agrees on {:.1}%", i + 1, seed, 100.0 * agreement))
+            .collect::<Vec<String>>()
+            .join("\n");
+        let plain = Combiner { registers: registers.clone(), ..self.clone() }.decrypt(ct)?;
+        Ok(format!("keystream: {} bits\n{}\nkey: {}\nplaintext: {}", bits.len(), lines, registers.describe(), plain))
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &str = "the geffe generator leaks its first and third registers";
+
+    fn lfsr(taps: &[usize], seed: u64) -> Lfsr {
+        Lfsr::from_taps(taps, seed, Mode::Fibonacci).unwrap()
+    }
+
+    fn combiner(kind: Kind, registers: &[(u64, &[usize])]) -> Combiner {
+        Combiner {
+            registers: registers.iter().map(|(s, t)| (*s, t.to_vec())).collect(),
+            ..Combiner::new(kind)
+        }
+    }
+
+    #[test]
+    fn test_geffe() {
+        assert_eq!(
+            (0..8).map(|i| geffe(i >> 2 & 1, i >> 1 & 1, i & 1)).collect::<Vec<u8>>(),
+            vec![0, 1, 0, 0, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_shrinking() {
+        let a = lfsr(&[4, 3], 0b1001);
+        let s = lfsr(&[5, 3], 0b10110);
+        let expected = a.clone().zip(s.clone()).filter(|(_, s)| *s == 1).map(|(a, _)| a).take(20).collect::<Vec<u8>>();
+        assert_eq!(Shrinking { a, s }.take(20).collect::<Vec<u8>>(), expected);
+    }
+
+    #[test]
+    fn test_self_shrinking() {
+        let a = lfsr(&[4, 3], 0b0011);
+        assert_eq!(SelfShrinking { a }.take(4).collect::<Vec<u8>>(), vec![1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn test_alternating() {
+        let control = lfsr(&[3, 2], 0b101);
+        let bits = Alternating::new(control, lfsr(&[4, 3], 1), lfsr(&[5, 3], 1)).take(3).collect::<Vec<u8>>();
+        assert_eq!(bits, vec![1, 0, 1]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let registers: [(u64, &[usize]); 3] = [(0x5b, &[7, 6]), (0xa7, &[8, 6, 5, 4]), (0x1d3, &[9, 5])];
+        for (kind, n) in [(Kind::Geffe, 3), (Kind::Shrinking, 2), (Kind::SelfShrinking, 1), (Kind::Alternating, 3)] {
+            let c = combiner(kind, &registers[..n]);
+            let ct = c.encrypt(TEXT).unwrap();
+            assert_eq!(c.decrypt(&ct), Ok(TEXT.to_string()));
+        }
+        assert!(combiner(Kind::Geffe, &registers[..2]).encrypt(TEXT).is_err());
+        assert_eq!(Combiner::new(Kind::Geffe).encrypt(TEXT), Err(CryptoError::EmptyKey));
+    }
+
+    #[test]
+    fn test_silent_control() {
+        let c = Combiner::new(Kind::SelfShrinking);
+        assert!(matches!(c.set_key(&KeyValue::Seeded(0x2, vec![2])), Err(CryptoError::InvalidKey(_))));
+        assert!(c.set_key(&KeyValue::Seeded(0x1, vec![2])).is_ok());
+        let c = Combiner::new(Kind::Shrinking);
+        assert!(matches!(
+            c.set_key(&KeyValue::Registers(vec![(0x5, vec![3, 2]), (0, vec![4, 3])])),
+            Err(CryptoError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_correlation_attack() {
+        let c = combiner(Kind::Geffe, &[(0x5b, &[7, 6]), (0xa7, &[8, 6, 5, 4]), (0x1d3, &[9, 5])]);
+        let ct = c.encrypt(TEXT).unwrap();
+        let guess = combiner(Kind::Geffe, &[(1, &[7, 6]), (1, &[8, 6, 5, 4]), (1, &[9, 5])]);
+        let report = guess.crack_known(&ct, TEXT).unwrap();
+        assert!(report.contains("key: 0x5b [7,6], 0xa7 [8,6,5,4], 0x1d3 [9,5]"), "{}", report);
+    }
+
+}
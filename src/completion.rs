@@ -0,0 +1,95 @@
+// Tab completion in the REPL
+
+use linefeed::{Completer, Completion, Prompter, Terminal};
+use cryptography::parser::commands::Command;
+use cryptography::parser::skip_text;
+use cryptography::registry;
+
+/// Commands followed by a quoted text that `with` may come after.
+const WITH: [&str; 4] = ["encrypt", "decrypt", "crack", "berlekamp"];
+
+/// The words that can follow `before`, the line up to the word being typed,
+/// and start with `word`.
+pub fn candidates(before: &str, word: &str) -> Vec<String> {
+    let words = before.split_whitespace().collect::<Vec<&str>>();
+    let res = match words.as_slice() {
+        ["select"] => registry::names(),
+        ["help", rest @ ..] => next_keyword(rest),
+        [command, ..] if WITH.contains(command) && after_text(before) => vec!["with"],
+        rest => next_keyword(rest),
+    };
+    res.into_iter()
+        .filter(|c| c.starts_with(word))
+        .map(|c| c.to_string())
+        .collect()
+}
+
+/// The next word of the commands whose keyword starts with `typed`.
+fn next_keyword(typed: &[&str]) -> Vec<&'static str> {
+    let mut res = Command::all()
+        .filter_map(|c| {
+            let keyword = c.keyword().split(' ').collect::<Vec<&str>>();
+            if keyword.len() > typed.len() && keyword[..typed.len()] == *typed {
+                Some(keyword[typed.len()])
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<&str>>();
+    res.dedup();
+    res
+}
+
+/// Whether `before` is a command followed by a whole text, quoted or heredoc,
+/// and a space.
+fn after_text(before: &str) -> bool {
+    let text = before.trim_start().split_once(char::is_whitespace).map_or("", |(_, rest)| rest.trim_start());
+    match skip_text(text) {
+        Some(rest) => !rest.is_empty() && rest.trim().is_empty(),
+        None => false
+    }
+}
+
+/// Completes the keywords of the commands, the names of the algorithms after
+/// `select` and `with` after a quoted text.
+pub struct CommandCompleter;
+
+impl<Term: Terminal> Completer<Term> for CommandCompleter {
+    fn complete(&self, word: &str, prompter: &Prompter<Term>, start: usize, _end: usize) -> Option<Vec<Completion>> {
+        let before = &prompter.buffer()[..start];
+        Some(candidates(before, word).into_iter().map(Completion::simple).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keywords() {
+        assert_eq!(candidates("", "s"), vec!["select", "set", "show", "save", "source", "status"]);
+        assert_eq!(candidates("show ", ""), vec!["key"]);
+        assert_eq!(candidates("help ", "en"), vec!["encrypt"]);
+        assert!(candidates("status ", "").is_empty());
+    }
+
+    #[test]
+    fn test_algorithms() {
+        assert_eq!(candidates("select ", "vi"), vec!["vigenere"]);
+        assert_eq!(candidates("select ", "").len(), registry::CIPHERS.len());
+    }
+
+    #[test]
+    fn test_with() {
+        assert_eq!(candidates("encrypt \"hello world\" ", "w"), vec!["with"]);
+        assert!(candidates("encrypt \"hello ", "w").is_empty());
+        assert!(candidates("message \"hello\" ", "").is_empty());
+        assert!(candidates("encrypt \"hello\"", "").is_empty());
+        assert_eq!(candidates("decrypt 'it\\'s \"here\"' ", ""), vec!["with"]);
+        assert!(candidates("decrypt 'it\\'s ", "").is_empty());
+        assert_eq!(candidates("encrypt \"say \\\"hi\\\"\" ", ""), vec!["with"]);
+        assert_eq!(candidates("encrypt <<END\nhello\nEND ", "w"), vec!["with"]);
+        assert!(candidates("encrypt <<END\nhello\n", "").is_empty());
+    }
+
+}
@@ -0,0 +1,328 @@
+// Cryptanalysis of the Vigenère cipher and of LFSR keystreams
+
+use std::collections::HashMap;
+use crate::alphabet::Alphabet;
+use crate::analysis;
+use crate::error::CryptoError;
+use crate::lfsr::{Lfsr, Mode, MAX_LENGTH};
+use crate::policy::Policy;
+use crate::tools::Zmod;
+
+/// What the attack found out about a ciphertext.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Crack {
+    /// Candidate key lengths with the number of Kasiski distances they divide.
+    pub kasiski: Vec<(usize, usize)>,
+    pub friedman: f64,
+    pub length: usize,
+    pub key: String,
+}
+
+/// The letters of `ct` with their position in the key stream, as the cipher
+/// sees them under `policy`.
+fn stream(ct: &str, alphabet: &Alphabet, policy: &Policy) -> Result<Vec<(usize, Zmod)>, CryptoError> {
+    let mut res = vec![];
+    policy.apply(ct, alphabet, |i, x| { res.push((i, x)); x })?;
+    Ok(res)
+}
+
+/// Kasiski examination: distances between repeated trigrams, and how many of
+/// them each key length from 2 to `max_length` divides.
+pub fn kasiski(stream: &[(usize, Zmod)], max_length: usize) -> Vec<(usize, usize)> {
+    let mut seen: HashMap<Vec<i64>, usize> = HashMap::new();
+    let mut distances = vec![];
+    for window in stream.windows(3) {
+        let trigram = window.iter().map(|(_, x)| x.value()).collect::<Vec<i64>>();
+        if let Some(previous) = seen.insert(trigram, window[0].0) {
+            distances.push(window[0].0 - previous);
+        }
+    }
+    let mut res = (2..=max_length)
+        .map(|l| (l, distances.iter().filter(|d| *d % l == 0).count()))
+        .collect::<Vec<(usize, usize)>>();
+    res.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    res
+}
+
+/// Friedman test: key length estimated from the index of coincidence.
+pub fn friedman(letters: &[char], alphabet: &Alphabet, policy: &Policy) -> f64 {
+    let n = letters.len() as f64;
+    let kp = policy.language.frequencies().iter().map(|f| (f / 100.0).powi(2)).sum::<f64>();
+    let kr = 1.0 / alphabet.len() as f64;
+    let ioc = analysis::index_of_coincidence(letters);
+    n * (kp - kr) / ((n - 1.0) * ioc + kp - n * kr)
+}
+
+fn columns(stream: &[(usize, Zmod)], length: usize) -> Vec<Vec<Zmod>> {
+    (0..length)
+        .map(|j| stream.iter().filter(|(i, _)| i % length == j).map(|(_, x)| *x).collect())
+        .collect()
+}
+
+fn column_ioc(stream: &[(usize, Zmod)], length: usize, alphabet: &Alphabet) -> f64 {
+    let cols = columns(stream, length);
+    cols.iter()
+        .map(|c| analysis::index_of_coincidence(&c.iter().map(|x| alphabet.number_to_letter(*x)).collect::<Vec<char>>()))
+        .sum::<f64>() / length as f64
+}
+
+/// The shift of one column, chosen by chi-squared against the language.
+fn best_shift(column: &[Zmod], alphabet: &Alphabet, policy: &Policy) -> Zmod {
+    let m = alphabet.len() as i64;
+    (0..m).map(|s| Zmod::new(s, m))
+        .map(|s| (policy.language.score(&column.iter().map(|x| alphabet.number_to_letter(*x - s)).collect::<String>()), s))
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, s)| s)
+        .unwrap_or(Zmod::new(0, m))
+}
+
+/// Estimate the key length of a Vigenère ciphertext and recover the key.
+pub fn crack_vigenere(ct: &str, alphabet: &Alphabet, policy: &Policy, max_length: usize) -> Result<Crack, CryptoError> {
+    let stream = stream(ct, alphabet, policy)?;
+    if stream.len() < 2 {
+        return Err(CryptoError::EmptyMessage);
+    }
+    let max_length = max_length.min(stream.len() / 2).max(1);
+    let iocs = (1..=max_length).map(|l| (l, column_ioc(&stream, l, alphabet))).collect::<Vec<(usize, f64)>>();
+    let best = iocs.iter().map(|(_, ioc)| *ioc).fold(0.0, f64::max);
+    let length = iocs.iter().find(|(_, ioc)| *ioc >= 0.9 * best).map(|(l, _)| *l).unwrap_or(1);
+    let key = columns(&stream, length).iter()
+        .map(|c| alphabet.number_to_letter(best_shift(c, alphabet, policy)))
+        .collect();
+    let letters = stream.iter().map(|(_, x)| alphabet.number_to_letter(*x)).collect::<Vec<char>>();
+    Ok(Crack {
+        kasiski: kasiski(&stream, max_length),
+        friedman: friedman(&letters, alphabet, policy),
+        length,
+        key
+    })
+}
+
+/// The shortest LFSR generating a sequence of bits.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Synthesis {
+    /// The linear complexity, that is the length of the register.
+    pub complexity: usize,
+    /// Exponents of the non-zero terms of the connection polynomial, highest first.
+    pub taps: Vec<usize>,
+}
+
+impl Synthesis {
+    /// The register that outputs `bits`, in the state before the first one.
+    pub fn lfsr(&self, bits: &[u8]) -> Result<Lfsr, CryptoError> {
+        if self.complexity > MAX_LENGTH {
+            return Err(CryptoError::InvalidKey(format!("a register of {} bits is not supported", self.complexity)));
+        }
+        let seed = bits.iter().take(self.complexity).enumerate()
+            .fold(0, |acc, (i, b)| acc | ((*b as u64 & 1) << i));
+        Lfsr::new(self.complexity, &self.taps, seed, Mode::Fibonacci)
+    }
+
+    pub fn polynomial(&self) -> String {
+        self.taps.iter()
+            .map(|t| if *t == 1 { "x".to_string() } else { format!("x^{}", t) })
+            .chain(std::iter::once("1".to_string()))
+            .collect::<Vec<String>>()
+            .join(" + ")
+    }
+}
+
+/// Berlekamp–Massey over GF(2): the linear complexity of `bits` and its
+/// minimal connection polynomial.
+pub fn berlekamp_massey(bits: &[u8]) -> Synthesis {
+    let n = bits.len();
+    let mut c = vec![0u8; n + 1];
+    let mut b = vec![0u8; n + 1];
+    c[0] = 1;
+    b[0] = 1;
+    let mut l = 0;
+    let mut m = 1;
+    for i in 0..n {
+        let discrepancy = (1..=l).fold(bits[i] & 1, |acc, j| acc ^ (c[j] & bits[i - j]));
+        if discrepancy == 0 {
+            m += 1;
+            continue;
+        }
+        let previous = c.clone();
+        for j in m..=n {
+            c[j] ^= b[j - m];
+        }
+        if 2 * l <= i {
+            l = i + 1 - l;
+            b = previous;
+            m = 1;
+        } else {
+            m += 1;
+        }
+    }
+    Synthesis {
+        complexity: l,
+        taps: (1..=l).rev().filter(|j| c[*j] == 1).collect()
+    }
+}
+
+/// The seed of the register given by `taps` whose output scores best under
+/// `agree`; every seed is tried, so the register is limited to 20 bits.
+fn best_seed<F: Fn(Lfsr) -> usize>(taps: &[usize], agree: F) -> Result<(u64, usize), CryptoError> {
+    let length = taps.iter().copied().max().unwrap_or(0);
+    if length > 20 {
+        return Err(CryptoError::InvalidKey(format!("every seed is tried, a register of {} bits is too long", length)));
+    }
+    (1..1u64 << length).try_fold((0, 0), |best, seed| {
+        let count = agree(Lfsr::from_taps(taps, seed, Mode::Fibonacci)?);
+        Ok(if count > best.1 { (seed, count) } else { best })
+    })
+}
+
+/// Correlation attack on the Geffe generator: the output agrees with the first
+/// and the third registers 75% of the time, so their seeds are found one at a
+/// time, then the second one is the seed that explains the whole keystream.
+/// Gives the seed of each register and how often it agrees with `bits`.
+pub fn crack_geffe(bits: &[u8], taps: &[Vec<usize>]) -> Result<Vec<(u64, f64)>, CryptoError> {
+    if taps.len() != 3 {
+        return Err(CryptoError::InvalidKey(format!("geffe needs 3 registers, not {}", taps.len())));
+    }
+    if bits.is_empty() {
+        return Err(CryptoError::EmptyMessage);
+    }
+    let correlation = |lfsr: Lfsr| lfsr.zip(bits).filter(|(x, z)| x == *z).count();
+    let (s1, c1) = best_seed(&taps[0], correlation)?;
+    let (s3, c3) = best_seed(&taps[2], correlation)?;
+    let x1 = Lfsr::from_taps(&taps[0], s1, Mode::Fibonacci)?;
+    let x3 = Lfsr::from_taps(&taps[2], s3, Mode::Fibonacci)?;
+    let (s2, c2) = best_seed(&taps[1], |x2| x1.clone().zip(x2).zip(x3.clone())
+        .map(|((a, b), c)| crate::combiner::geffe(a, b, c))
+        .zip(bits)
+        .filter(|(x, z)| x == *z)
+        .count())?;
+    let n = bits.len() as f64;
+    Ok(vec![(s1, c1 as f64 / n), (s2, c2 as f64 / n), (s3, c3 as f64 / n)])
+}
+
+/// Run Berlekamp–Massey on a keystream and predict the bits that follow it.
+pub fn report_lfsr(bits: &[u8], predict: usize) -> Result<String, CryptoError> {
+    if bits.is_empty() {
+        return Err(CryptoError::EmptyMessage);
+    }
+    let synthesis = berlekamp_massey(bits);
+    let mut lines = vec![
+        format!("bits: {}", bits.len()),
+        format!("linear complexity: {}", synthesis.complexity)
+    ];
+    if synthesis.complexity == 0 {
+        lines.push("the sequence is all zeros".to_string());
+        return Ok(lines.join("\n"));
+    }
+    if 2 * synthesis.complexity > bits.len() {
+        lines.push(format!("warning: {} bits are needed for the polynomial to be unique", 2 * synthesis.complexity));
+    }
+    match synthesis.lfsr(bits) {
+        Ok(lfsr) => {
+            lines.push(format!("polynomial: {}", lfsr.polynomial()));
+            lines.push(format!("seed: {:#x}", lfsr.state()));
+            if lfsr.taps().first() == Some(&synthesis.complexity) {
+                lines.push(format!("key: {:#x} [{}]", lfsr.state(),
+                    lfsr.taps().iter().map(|t| t.to_string()).collect::<Vec<String>>().join(",")));
+            }
+            let next = lfsr.skip(bits.len()).take(predict).map(|b| b.to_string()).collect::<String>();
+            lines.push(format!("next: {}", next));
+        },
+        Err(_) if synthesis.complexity > MAX_LENGTH => {
+            lines.push(format!("polynomial: {}", synthesis.polynomial()));
+            lines.push(format!("no register: at most {} bits are supported", MAX_LENGTH));
+        },
+        Err(_) => lines.push(format!("no register of {} bits outputs this sequence", synthesis.complexity))
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base_cryptography::Cipher;
+    use crate::vigenere::Vigenere;
+
+    const TEXT: &str = "It was the best of times, it was the worst of times, it was the age of wisdom, \
+        it was the age of foolishness, it was the epoch of belief, it was the epoch of incredulity, \
+        it was the season of Light, it was the season of Darkness, it was the spring of hope, \
+        it was the winter of despair, we had everything before us, we had nothing before us, \
+        we were all going direct to Heaven, we were all going direct the other way.";
+
+    fn encrypt(key: &str) -> String {
+        Vigenere { key: key.to_string(), message: TEXT.to_string(), alphabet: Alphabet::default(), policy: Policy::default() }
+            .encrypt2().unwrap()
+    }
+
+    #[test]
+    fn test_crack_vigenere() {
+        for key in ["lemon", "crypto", "key"] {
+            let crack = crack_vigenere(&encrypt(key), &Alphabet::default(), &Policy::default(), 12).unwrap();
+            assert_eq!(crack.key, key);
+        }
+    }
+
+    #[test]
+    fn test_kasiski() {
+        let ct = encrypt("lemon");
+        let stream = stream(&ct, &Alphabet::default(), &Policy::default()).unwrap();
+        assert_eq!(kasiski(&stream, 12)[0].0, 5);
+    }
+
+    #[test]
+    fn test_berlekamp_massey() {
+        let lfsr = Lfsr::from_taps(&[16, 14, 13, 11], 0xACE1, Mode::Fibonacci).unwrap();
+        let bits = lfsr.clone().take(64).collect::<Vec<u8>>();
+        let synthesis = berlekamp_massey(&bits);
+        assert_eq!(synthesis, Synthesis { complexity: 16, taps: vec![16, 14, 13, 11] });
+        assert_eq!(synthesis.lfsr(&bits), Ok(lfsr));
+    }
+
+    #[test]
+    fn test_berlekamp_massey_galois() {
+        let lfsr = Lfsr::from_taps(&[5, 3], 0b10011, Mode::Galois).unwrap();
+        let bits = lfsr.clone().take(100).collect::<Vec<u8>>();
+        let synthesis = berlekamp_massey(&bits[..20]);
+        assert_eq!(synthesis.complexity, 5);
+        let predicted = synthesis.lfsr(&bits[..20]).unwrap().take(100).collect::<Vec<u8>>();
+        assert_eq!(predicted, bits);
+    }
+
+    #[test]
+    fn test_linear_complexity() {
+        assert_eq!(berlekamp_massey(&[0, 0, 0, 0]).complexity, 0);
+        assert_eq!(berlekamp_massey(&[0, 0, 0, 1]).complexity, 4);
+        assert_eq!(berlekamp_massey(&[1, 1, 1, 1]), Synthesis { complexity: 1, taps: vec![1] });
+    }
+
+    #[test]
+    fn test_report_lfsr() {
+        let bits = Lfsr::from_taps(&[4, 3], 0b1001, Mode::Fibonacci).unwrap().take(12).collect::<Vec<u8>>();
+        let report = report_lfsr(&bits, 4).unwrap();
+        assert!(report.contains("linear complexity: 4"), "{}", report);
+        assert!(report.contains("key: 0x9 [4,3]"), "{}", report);
+        assert_eq!(report_lfsr(&[], 4), Err(CryptoError::EmptyMessage));
+    }
+
+    #[test]
+    fn test_report_long_sequence() {
+        let mut x = 1u64;
+        let bits = (0..300).map(|_| {
+            x = x.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (x >> 63) as u8
+        }).collect::<Vec<u8>>();
+        let synthesis = berlekamp_massey(&bits);
+        assert!(synthesis.complexity > 130, "{}", synthesis.complexity);
+        assert!(synthesis.lfsr(&bits).is_err());
+        let report = report_lfsr(&bits, 8).unwrap();
+        assert!(report.contains(&format!("polynomial: x^{}", synthesis.complexity)), "{}", report);
+        assert!(report.contains("no register: at most 64 bits are supported"), "{}", report);
+    }
+
+    #[test]
+    fn test_friedman() {
+        let letters = analysis::letters(&encrypt("lemon"), &Alphabet::default());
+        let estimate = friedman(&letters, &Alphabet::default(), &Policy::default());
+        assert!(estimate > 2.0 && estimate < 8.0, "{}", estimate);
+    }
+
+}
@@ -0,0 +1,84 @@
+// Errors returned by the ciphers, the parser and the session
+
+use std::fmt;
+use crate::parser::commands::Command;
+use crate::registry;
+
+/// Everything that can go wrong while parsing or running a command.
+#[derive(PartialEq, Debug, Clone)]
+pub enum CryptoError {
+    UnsupportedKey { algo: String, key: String },
+    InvalidCharacter(char),
+    KeyOutOfRange(String),
+    EmptyKey,
+    InvalidKey(String),
+    NotInvertible { key: String, modulus: i64 },
+    InvalidAlphabet(String),
+    UnsupportedAlphabet { algo: String, alphabet: String },
+    InvalidOption { option: String, value: String },
+    Parse { position: usize, found: String },
+    UnknownAlgorithm { name: String, suggestions: Vec<String> },
+    NoAlgorithm,
+    EmptyMessage,
+    InvalidHex(String),
+    UnsupportedOperation { algo: String, operation: String },
+    NotEnoughBits { needed: usize, found: usize },
+    UnknownCommand(String),
+    InvalidSession { line: usize, reason: String },
+    Io { path: String, reason: String },
+    Usage(String),
+    Script { line: usize, error: Box<CryptoError> },
+    Sourced { path: String, error: Box<CryptoError>, transcript: String },
+    RecursiveSource(String),
+    Failed(usize),
+    SourceTooDeep,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CryptoError::UnsupportedKey { algo, key } =>
+                write!(f, "the algorithm {} doesn't work with a key {}. hint: try to change the key type", algo, key),
+            CryptoError::InvalidCharacter(c) => write!(f, "invalid character {:?}", c),
+            CryptoError::UnsupportedAlphabet { algo, alphabet } =>
+                write!(f, "the algorithm {} doesn't work with the {} alphabet", algo, alphabet),
+            CryptoError::InvalidOption { option, value } =>
+                write!(f, "invalid value {:?} for the option {:?}", value, option),
+            CryptoError::KeyOutOfRange(k) => write!(f, "the key {} is out of range", k),
+            CryptoError::EmptyKey => write!(f, "the key is empty"),
+            CryptoError::InvalidKey(reason) => write!(f, "invalid key: {}", reason),
+            CryptoError::NotInvertible { key, modulus } =>
+                write!(f, "the key {} is not invertible modulo {}", key, modulus),
+            CryptoError::InvalidAlphabet(a) =>
+                write!(f, "invalid alphabet \"{}\": it needs at least two distinct letters", a),
+            CryptoError::Parse { position, found } if found.is_empty() =>
+                write!(f, "parse error at position {}: unexpected end of input", position),
+            CryptoError::Parse { position, found } =>
+                write!(f, "parse error at position {}: unexpected {:?}", position, found),
+            CryptoError::UnknownAlgorithm { name, suggestions } if suggestions.is_empty() =>
+                write!(f, "unknown algorithm \"{}\" (available: {})", name, registry::names().join(", ")),
+            CryptoError::UnknownAlgorithm { name, suggestions } =>
+                write!(f, "unknown algorithm \"{}\", did you mean {}?", name, suggestions.join(" or ")),
+            CryptoError::NoAlgorithm => write!(f, "no algorithm selected. hint: use select"),
+            CryptoError::EmptyMessage => write!(f, "the message is empty"),
+            CryptoError::InvalidHex(h) => write!(f, "{:?} is not an even number of hex digits", h),
+            CryptoError::UnsupportedOperation { algo, operation } =>
+                write!(f, "{} is not available for the algorithm {}", operation, algo),
+            CryptoError::NotEnoughBits { needed, found } =>
+                write!(f, "at least {} bits are needed, found {}", needed, found),
+            CryptoError::UnknownCommand(c) => write!(f, "unknown command \"{}\" (available: {})", c,
+                Command::all().map(|c| c.keyword()).collect::<Vec<&str>>().join(", ")),
+            CryptoError::InvalidSession { line, reason } => write!(f, "session line {}: {}", line, reason),
+            CryptoError::Io { path, reason } => write!(f, "{}: {}", path, reason),
+            CryptoError::Usage(reason) => write!(f, "{}", reason),
+            CryptoError::Script { line, error } => write!(f, "line {}: {}", line, error),
+            CryptoError::Failed(1) => write!(f, "1 command failed"),
+            CryptoError::Failed(n) => write!(f, "{} commands failed", n),
+            CryptoError::Sourced { path, error, transcript } => write!(f, "{}: {}\n{}", path, error, transcript),
+            CryptoError::RecursiveSource(path) => write!(f, "recursive source of {}", path),
+            CryptoError::SourceTooDeep => write!(f, "source nested too deeply"),
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
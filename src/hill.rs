@@ -0,0 +1,337 @@
+// Hill cipher: blocks of letters multiplied by an invertible matrix
+
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::{Key, Cipher};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::policy::Policy;
+use crate::registry::CipherDescriptor;
+use crate::tools::Zmod;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "hill",
+    aliases: &[],
+    key_type: "matrix",
+    description: "multiply blocks of letters by an invertible n x n matrix",
+    build: || Box::new(Hill { key: vec![vec![1]], message: "".to_string(), alphabet: Alphabet::default(), policy: Policy::default() }),
+};
+
+pub type Matrix = Vec<Vec<i64>>;
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Hill {
+    pub key: Matrix,
+    pub message: String,
+    pub alphabet: Alphabet,
+    pub policy: Policy
+}
+
+impl Key for Matrix {
+    fn parse(value: &KeyValue) -> Option<Matrix> {
+        match value {
+            KeyValue::Matrix(m) => Some(m.clone()),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("[{}]", self.iter()
+            .map(|row| format!("[{}]", row.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(",")))
+            .collect::<Vec<String>>()
+            .join(","))
+    }
+}
+
+type Rows = Vec<Vec<Zmod>>;
+
+fn rows(m: &Matrix, modulus: i64) -> Rows {
+    m.iter().map(|row| row.iter().map(|x| Zmod::new(*x, modulus)).collect()).collect()
+}
+
+/// Bring the first `n` columns of `rows` to an upper triangular form, with
+/// Euclid's algorithm on each column since the modulus needs not be prime.
+/// Returns the sign of the row swaps.
+fn triangulate(rows: &mut Rows, n: usize, modulus: i64) -> Zmod {
+    let mut sign = Zmod::new(1, modulus);
+    for k in 0..n {
+        loop {
+            let pivot = (k..n).filter(|i| rows[*i][k].value() != 0).min_by_key(|i| rows[*i][k].value());
+            let Some(pivot) = pivot else { break };
+            if pivot != k {
+                rows.swap(pivot, k);
+                sign = -sign;
+            }
+            let mut done = true;
+            for i in k + 1..n {
+                let q = Zmod::new(rows[i][k].value() / rows[k][k].value(), modulus);
+                let pivot_row = rows[k].clone();
+                rows[i].iter_mut().zip(pivot_row).for_each(|(x, p)| *x = *x - q * p);
+                done &= rows[i][k].value() == 0;
+            }
+            if done {
+                break;
+            }
+        }
+    }
+    sign
+}
+
+/// Determinant modulo `modulus`, by elimination.
+pub fn determinant(m: &Matrix, modulus: i64) -> Zmod {
+    let mut rows = rows(m, modulus);
+    let sign = triangulate(&mut rows, m.len(), modulus);
+    (0..m.len()).fold(sign, |acc, i| acc * rows[i][i])
+}
+
+/// Inverse modulo `modulus`, or `None` when the determinant is not invertible.
+pub fn inverse(m: &Matrix, modulus: i64) -> Option<Matrix> {
+    let n = m.len();
+    let mut rows = rows(m, modulus);
+    for (i, row) in rows.iter_mut().enumerate() {
+        row.extend((0..n).map(|j| Zmod::new((i == j) as i64, modulus)));
+    }
+    triangulate(&mut rows, n, modulus);
+    for k in (0..n).rev() {
+        let pivot = rows[k][k].inverse()?;
+        rows[k].iter_mut().for_each(|x| *x = *x * pivot);
+        for i in 0..k {
+            let q = rows[i][k];
+            let pivot_row = rows[k].clone();
+            rows[i].iter_mut().zip(pivot_row).for_each(|(x, p)| *x = *x - q * p);
+        }
+    }
+    Some(rows.iter().map(|row| row[n..].iter().map(|x| x.value()).collect()).collect())
+}
+
+pub fn multiply(a: &Matrix, b: &Matrix, modulus: i64) -> Matrix {
+    (0..a.len()).map(|i| (0..b[0].len()).map(|j| {
+        (0..b.len())
+            .map(|k| Zmod::new(a[i][k], modulus) * Zmod::new(b[k][j], modulus))
+            .fold(Zmod::new(0, modulus), |acc, x| acc + x)
+            .value()
+    }).collect()).collect()
+}
+
+fn check_key(key: &Matrix, alphabet: &Alphabet) -> Result<Matrix, CryptoError> {
+    let n = key.len();
+    if n == 0 || key.iter().any(|row| row.len() != n) {
+        return Err(CryptoError::InvalidKey(format!("{} is not a square matrix", key.describe())));
+    }
+    inverse(key, alphabet.len() as i64).ok_or(CryptoError::NotInvertible {
+        key: key.describe(),
+        modulus: alphabet.len() as i64
+    })
+}
+
+impl Hill {
+    fn compute(&self, msg: &str, key: &Matrix) -> Result<String, CryptoError> {
+        let modulus = self.alphabet.len() as i64;
+        let n = key.len();
+        let mut letters = vec![];
+        self.policy.apply(msg, &self.alphabet, |_, x| { letters.push(x.value()); x })?;
+        let filler = self.alphabet.letter_to_number('x').map(|x| x.value()).unwrap_or(modulus - 1);
+        while letters.len() % n != 0 {
+            letters.push(filler);
+        }
+        let out = letters.chunks(n)
+            .flat_map(|block| multiply(key, &block.iter().map(|x| vec![*x]).collect(), modulus))
+            .map(|row| Zmod::new(row[0], modulus))
+            .collect::<Vec<Zmod>>();
+        let mut next = out.iter();
+        let mut res = self.policy.apply(msg, &self.alphabet, |_, x| *next.next().unwrap_or(&x))?;
+        res.extend(next.map(|x| self.alphabet.number_to_letter(*x)));
+        Ok(res)
+    }
+}
+
+/// How many sets of blocks `recover_key` tries before giving up.
+const MAX_TRIES: usize = 10_000;
+
+/// Recover an `n` x `n` key from a known plaintext and its ciphertext, using
+/// the first set of `n` blocks that forms an invertible matrix, among the
+/// first `MAX_TRIES` sets.
+pub fn recover_key(plain: &str, cipher: &str, n: usize, alphabet: &Alphabet) -> Result<Matrix, CryptoError> {
+    let modulus = alphabet.len() as i64;
+    let numbers = |s: &str| s.chars()
+        .filter(|c| alphabet.contains(*c))
+        .map(|c| alphabet.letter_to_number(c).map(|x| x.value()))
+        .collect::<Result<Vec<i64>, CryptoError>>();
+    let p = numbers(plain)?;
+    let c = numbers(cipher)?;
+    let blocks = p.len().min(c.len()) / n;
+    let (mut chosen, mut tries) = (vec![], MAX_TRIES);
+    if let Some(blocks) = choose(blocks, n, &mut chosen, &mut tries, &|chosen: &[usize]| {
+        inverse(&columns(&p, chosen, n), modulus).is_some()
+    }) {
+        let p_inverse = inverse(&columns(&p, &blocks, n), modulus).unwrap_or_default();
+        return Ok(multiply(&columns(&c, &blocks, n), &p_inverse, modulus));
+    }
+    Err(CryptoError::InvalidKey(format!("not enough independent blocks of {} letters", n)))
+}
+
+/// The matrix whose columns are the chosen blocks of `text`.
+fn columns(text: &[i64], blocks: &[usize], n: usize) -> Matrix {
+    (0..n).map(|i| blocks.iter().map(|b| text[b * n + i]).collect()).collect()
+}
+
+fn choose(total: usize, n: usize, chosen: &mut Vec<usize>, tries: &mut usize, ok: &dyn Fn(&[usize]) -> bool) -> Option<Vec<usize>> {
+    if chosen.len() == n {
+        *tries = tries.saturating_sub(1);
+        return if ok(chosen) { Some(chosen.clone()) } else { None };
+    }
+    let start = chosen.last().map(|x| x + 1).unwrap_or(0);
+    for i in start..total {
+        if *tries == 0 {
+            break;
+        }
+        chosen.push(i);
+        if let Some(res) = choose(total, n, chosen, tries, ok) {
+            return Some(res);
+        }
+        chosen.pop();
+    }
+    None
+}
+
+impl Cipher for Hill {
+    fn name(&self) -> &'static str {
+        "hill"
+    }
+
+    fn title(&self) -> &'static str {
+        "Hill"
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        self.compute(msg, &self.key)
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        self.compute(ct, &check_key(&self.key, &self.alphabet)?)
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let value = Matrix::from_key(self.name(), key)?;
+        check_key(&value, &self.alphabet)?;
+        Ok(Box::new(Hill {
+            key: value,
+            ..self.clone()
+        }))
+    }
+
+    fn describe(&self) -> String {
+        self.key.describe()
+    }
+
+    fn show_key(&self) -> String {
+        self.key.iter()
+            .map(|row| row.iter().map(|x| format!("{:>3}", x)).collect::<Vec<String>>().join(" "))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Hill {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        Some(&self.alphabet)
+    }
+
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        check_key(&self.key, alphabet)?;
+        Ok(Box::new(Hill {
+            alphabet: alphabet.clone(),
+            ..self.clone()
+        }))
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        Box::new(Hill {
+            policy,
+            ..self.clone()
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hill(key: Matrix, message: &str) -> Hill {
+        Hill { key, message: message.to_string(), alphabet: Alphabet::default(), policy: Policy::default() }
+    }
+
+    #[test]
+    fn test_matrix_inverse() {
+        let k = vec![vec![3, 3], vec![2, 5]];
+        assert_eq!(determinant(&k, 26).value(), 9);
+        assert_eq!(inverse(&k, 26), Some(vec![vec![15, 17], vec![20, 9]]));
+        assert_eq!(inverse(&vec![vec![2, 0], vec![0, 1]], 26), None);
+        assert_eq!(determinant(&vec![vec![1, i64::MIN], vec![0, 1]], 26).value(), 1);
+        let k = vec![vec![2, 13], vec![13, 2]];
+        assert_eq!(determinant(&k, 26).value(), 17);
+        assert_eq!(inverse(&k, 26).map(|i| multiply(&k, &i, 26)), Some(vec![vec![1, 0], vec![0, 1]]));
+        let k = vec![vec![6, 24, 1], vec![13, 16, 10], vec![20, 17, 15]];
+        assert_eq!(determinant(&k, 26).value(), 25);
+        assert_eq!(inverse(&k, 26), Some(vec![vec![8, 5, 10], vec![21, 8, 21], vec![21, 12, 8]]));
+        let big = (0..40).map(|i| (0..40).map(|j| (i == j || j == i + 1) as i64).collect()).collect();
+        assert_eq!(determinant(&big, 26).value(), 1);
+    }
+
+    #[test]
+    fn test_hill_encrypt() {
+        assert_eq!(
+            hill(vec![vec![3, 3], vec![2, 5]], "help").encrypt2(),
+            Ok("hiat".to_string()));
+        assert_eq!(
+            hill(vec![vec![6, 24, 1], vec![13, 16, 10], vec![20, 17, 15]], "act").encrypt2(),
+            Ok("poh".to_string()));
+    }
+
+    #[test]
+    fn test_hill_padding() {
+        let h = hill(vec![vec![3, 3], vec![2, 5]], "Help you!");
+        let c = h.encrypt2().unwrap();
+        assert_eq!(c.chars().count(), 10);
+        assert_eq!(h.decrypt(&c), Ok("Help you!x".to_string()));
+    }
+
+    #[test]
+    fn test_hill_invalid_key() {
+        let h = hill(vec![vec![1]], "");
+        assert!(matches!(
+            h.set_key(&KeyValue::Matrix(vec![vec![2, 4], vec![1, 3]])),
+            Err(CryptoError::NotInvertible { .. })));
+        assert!(matches!(
+            h.set_key(&KeyValue::Matrix(vec![vec![1, 2]])),
+            Err(CryptoError::InvalidKey(_))));
+    }
+
+    #[test]
+    fn test_recover_key() {
+        let key = vec![vec![6, 24, 1], vec![13, 16, 10], vec![20, 17, 15]];
+        let plain = "thequickbrownfoxjumpsoverthelazydog";
+        let cipher = hill(key.clone(), plain).encrypt2().unwrap();
+        assert_eq!(recover_key(plain, &cipher, 3, &Alphabet::default()), Ok(key));
+        assert!(recover_key("aaaa", "aaaa", 2, &Alphabet::default()).is_err());
+        let same = "a".repeat(2000);
+        assert!(recover_key(&same, &same, 8, &Alphabet::default()).is_err());
+    }
+
+}
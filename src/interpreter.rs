@@ -0,0 +1,204 @@
+// Evaluation of the commands typed in the REPL
+
+use crate::analysis;
+use crate::cryptanalysis;
+use crate::base_cryptography::Algo;
+use crate::error::CryptoError;
+use crate::parser::{parse, commands::{Bits, Command}};
+use crate::randomness;
+use crate::registry;
+use crate::script;
+use crate::session::Session;
+use crate::stream;
+
+/// Select the algorithm `algo`, keeping `context` when the name is unknown.
+pub fn select_algo(algo: &str, context: &Session) -> (Session, Result<String, CryptoError>) {
+    match context.select(algo) {
+        Ok(session) => {
+            let msg = format!("{} algorithm selected.", session.algo.get_title());
+            (session, Ok(msg))
+        },
+        Err(e) => (context.clone(), Err(e))
+    }
+}
+
+/// General help, listing the commands and the available algorithms, or the
+/// help of the command `rest`.
+pub fn help_message(rest: &str, context: &Session) -> (Session, Result<String, CryptoError>) {
+    if !rest.is_empty() {
+        let help = Command::all()
+            .find(|c| c.keyword() == rest)
+            .map(|c| format!("{}: {}", c.keyword(), c.describe()))
+            .ok_or(CryptoError::UnknownCommand(rest.to_string()));
+        return (context.clone(), help);
+    }
+    let algos = registry::CIPHERS.iter()
+        .map(|d| format!("\t{}", d.describe()))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let commands = Command::all().map(|c| c.keyword()).collect::<Vec<&str>>().join(", ");
+    let help = format!("commands: {}\nhelp <command> describes one of them\nalgorithms:\n{}", commands, algos);
+   (context.clone(), Ok(help))
+}
+
+fn update(context: &Session, res: Result<Session, CryptoError>, msg: &str) -> (Session, Result<String, CryptoError>) {
+    match res {
+        Ok(session) => (session, Ok(msg.to_string())),
+        Err(e) => (context.clone(), Err(e))
+    }
+}
+
+/// Run a command whose output becomes the last result of the session.
+fn record(context: &Session, res: Result<String, CryptoError>) -> (Session, Result<String, CryptoError>) {
+    (context.record(&res), res)
+}
+
+/// Parse and run one command line, returning the new context and the output.
+pub fn eval(input: &str, context: &Session) -> (Session, Result<String, CryptoError>) {
+   let algo = &context.algo;
+   match parse(input) {
+       Err(e) => (context.clone(), Err(e)),
+       Ok(Command::Exit) => (Session { algo: Algo::Exit, ..context.clone() }, Ok("exit".to_string())),
+       Ok(Command::Select(name)) => select_algo(&name, context),
+       Ok(Command::Encrypt(None)) => record(context, algo.encrypt2()),
+       Ok(Command::Encrypt(Some((keyvalue, m)))) => record(context, algo.encrypt(&m, keyvalue)),
+       Ok(Command::Decrypt(None)) => record(context, algo.decrypt2()),
+       Ok(Command::Decrypt(Some((keyvalue, m)))) => record(context, algo.decrypt(&m, keyvalue)),
+       Ok(Command::Key(k)) => update(context, context.set_key(k), "Key value set"),
+       Ok(Command::Message(m)) => (context.set_message(&m), Ok("Message set".to_string())),
+       Ok(Command::Alphabet(None)) => (context.clone(), Ok(context.get_alphabet().letters().iter().collect())),
+       Ok(Command::Alphabet(Some(a))) => update(context, context.set_alphabet(&a), &format!("Alphabet set to {}", a.describe())),
+       Ok(Command::Set(o, v)) => update(context, context.set_option(&o, &v), &format!("{} set to {}", o, v)),
+       Ok(Command::ShowKey) => (context.clone(), algo.show_key()),
+       Ok(Command::Analyze(text)) => {
+           let text = text.unwrap_or_else(|| context.message.clone());
+           (context.clone(), Ok(analysis::report(&text, &context.get_alphabet(), context.policy.language)))
+       },
+       Ok(Command::Crack(text, known)) => record(context, algo.crack(text, known)),
+       Ok(Command::Berlekamp(text, plain)) => {
+           let bits = match plain {
+               Some(p) => stream::keystream(&text, &p),
+               None => stream::bits(&text)
+           };
+           (context.clone(), bits.and_then(|b| cryptanalysis::report_lfsr(&b, 32)))
+       },
+       Ok(Command::Randomness(bits)) => {
+           let bits = match bits {
+               Bits::Keystream(n) => algo.keystream(n),
+               Bits::File(path) => randomness::read_bits(&path)
+           };
+           (context.clone(), bits.map(|b| randomness::report(&b)))
+       },
+       Ok(Command::SaveSession(path)) => {
+           let res = std::fs::write(&path, context.save())
+               .map(|_| format!("Session saved to {}", path))
+               .map_err(|e| CryptoError::Io { path: path.clone(), reason: e.to_string() });
+           (context.clone(), res)
+       },
+       Ok(Command::LoadSession(path)) => {
+           let session = std::fs::read_to_string(&path)
+               .map_err(|e| CryptoError::Io { path: path.clone(), reason: e.to_string() })
+               .and_then(|text| Session::load(&text))
+               .map(|session| Session { sources: context.sources.clone(), ..session });
+           update(context, session, &format!("Session loaded from {}", path))
+       },
+       Ok(Command::Source(path, on_error)) => script::source(&path, context, on_error),
+       Ok(Command::Status) => (context.clone(), Ok(context.get_status())),
+       Ok(Command::Help(command)) => help_message(&command.unwrap_or_default(), context),
+       Ok(Command::Empty) => (context.clone(), Ok("".to_string()))
+   }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_session() {
+        let (context, _) = eval("select vigenere", &Session::new());
+        let (context, _) = eval("key \"bc\"", &context);
+        let (context, _) = eval("message \"hello\"", &context);
+        assert_eq!(
+            eval("encrypt", &context).1,
+            Ok("igmnp".to_string()));
+    }
+
+    #[test]
+    fn test_eval_alphabet() {
+        let (context, _) = eval("select cesar", &Session::new());
+        let (context, _) = eval("alphabet mixed", &context);
+        let (context, _) = eval("select vigenere", &context);
+        let (context, _) = eval("key \"aB\"", &context);
+        assert_eq!(
+            eval("encrypt \"Hello\" with \"aB\"", &context).1,
+            Ok("HFlMo".to_string()));
+        assert_eq!(context.get_alphabet().name(), "mixed");
+    }
+
+    #[test]
+    fn test_eval_brute_force() {
+        let (context, _) = eval("select cesar", &Session::new());
+        let table = eval("decrypt \"wkh vhfuhw phvvdjh lv kxqw wkh wuhdvxuh\" with 0..25", &context).1.unwrap();
+        let best = table.lines().nth(1).unwrap();
+        assert!(best.ends_with("the secret message is hunt the treasure"), "{}", table);
+        assert_eq!(table.lines().count(), 27);
+        assert!(matches!(
+            eval("decrypt \"wkh\" with 25..0", &context).1,
+            Err(CryptoError::InvalidKey(_))));
+        let (context, _) = eval("select vigenere", &context);
+        assert!(matches!(
+            eval("decrypt \"abc\" with 0..25", &context).1,
+            Err(CryptoError::UnsupportedKey { .. })));
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let (context, res) = eval("key 3", &Session::new());
+        assert_eq!(res, Err(CryptoError::NoAlgorithm));
+        let (context, _) = eval("select cesar", &context);
+        let (context, res) = eval("key \"abc\"", &context);
+        assert!(matches!(res, Err(CryptoError::UnsupportedKey { .. })));
+        assert_eq!(context.algo.get_algo(), "cesar".to_string());
+    }
+
+    #[test]
+    fn test_eval_carries_state() {
+        let (context, _) = eval("message \"hello\"", &Session::new());
+        let (context, _) = eval("select vigenere", &context);
+        let (context, _) = eval("key \"bc\"", &context);
+        let (context, _) = eval("encrypt", &context);
+        let (context, _) = eval("select vig", &context);
+        assert_eq!(context.last, Some("igmnp".to_string()));
+        assert!(context.get_status().starts_with("algo: vigenere | key: \"bc\" | message: hello | last: igmnp"));
+    }
+
+    #[test]
+    fn test_eval_help() {
+        assert_eq!(
+            eval("help show key", &Session::new()).1,
+            Ok("show key: Show the key of the current algorithm, as a table when it has one".to_string()));
+        assert!(matches!(eval("help foo", &Session::new()).1, Err(CryptoError::UnknownCommand(_))));
+    }
+
+    #[test]
+    fn test_eval_save_load() {
+        let path = std::env::temp_dir().join(format!("cryptography-{}.session", std::process::id()));
+        let path = path.to_str().unwrap();
+        let (context, _) = eval("select vigenere", &Session::new());
+        let (context, _) = eval("key \"bc\"", &context);
+        let (context, _) = eval("message \"hello\"", &context);
+        let (context, _) = eval("encrypt", &context);
+        assert!(eval(&format!("save session {}", path), &context).1.is_ok());
+        let (loaded, res) = eval(&format!("load session {}", path), &Session::new());
+        std::fs::remove_file(path).unwrap();
+        assert!(res.is_ok(), "{:?}", res);
+        assert_eq!(loaded.get_status(), context.get_status());
+        assert!(matches!(eval("load session /nonexistent/file", &context).1, Err(CryptoError::Io { .. })));
+    }
+
+    #[test]
+    fn test_eval_exit() {
+        assert!(matches!(eval("exit", &Session::new()).0.algo, Algo::Exit));
+    }
+
+}
@@ -0,0 +1,107 @@
+// Letter frequencies of the reference languages
+
+use crate::error::CryptoError;
+
+/// A reference language for scoring candidate plaintexts.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Language {
+    English,
+    French,
+}
+
+const ENGLISH: [f64; 26] = [
+    8.167, 1.492, 2.782, 4.253, 12.702, 2.228, 2.015, 6.094, 6.966, 0.153,
+    0.772, 4.025, 2.406, 6.749, 7.507, 1.929, 0.095, 5.987, 6.327, 9.056,
+    2.758, 0.978, 2.360, 0.150, 1.974, 0.074 ];
+
+const FRENCH: [f64; 26] = [
+    7.636, 0.901, 3.260, 3.669, 14.715, 1.066, 0.866, 0.737, 7.529, 0.613,
+    0.074, 5.456, 2.968, 7.095, 5.796, 2.521, 1.362, 6.693, 7.948, 7.244,
+    6.311, 1.838, 0.049, 0.427, 0.128, 0.326 ];
+
+impl Language {
+    pub fn named(name: &str) -> Result<Language, CryptoError> {
+        match name {
+            "english" | "en" => Ok(Language::English),
+            "french" | "fr" => Ok(Language::French),
+            _ => Err(CryptoError::InvalidOption { option: "language".to_string(), value: name.to_string() })
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Language::English => "english",
+            Language::French => "french",
+        }
+    }
+
+    /// Expected frequency of each letter from `a` to `z`, in percent.
+    pub fn frequencies(&self) -> &'static [f64; 26] {
+        match self {
+            Language::English => &ENGLISH,
+            Language::French => &FRENCH,
+        }
+    }
+
+    /// Chi-squared distance between the letters of `text` and the language;
+    /// the lower, the more plausible.
+    pub fn score(&self, text: &str) -> f64 {
+        let counts = letter_counts(text);
+        let total = counts.iter().sum::<usize>() as f64;
+        if total == 0.0 {
+            return f64::INFINITY;
+        }
+        self.frequencies().iter().zip(counts.iter())
+            .map(|(f, c)| {
+                let expected = f * total / 100.0;
+                (*c as f64 - expected).powi(2) / expected
+            })
+            .sum()
+    }
+}
+
+/// Base latin letter of `c`, ignoring case and the usual accents.
+pub fn fold(c: char) -> Option<char> {
+    let c = c.to_lowercase().next()?;
+    let base = match c {
+        'à' | 'â' | 'ä' => 'a',
+        'ç' => 'c',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'î' | 'ï' => 'i',
+        'ô' | 'ö' => 'o',
+        'ù' | 'û' | 'ü' => 'u',
+        'ÿ' => 'y',
+        c => c
+    };
+    if base.is_ascii_lowercase() { Some(base) } else { None }
+}
+
+/// Occurrences of each letter from `a` to `z` in `text`.
+pub fn letter_counts(text: &str) -> [usize; 26] {
+    let mut counts = [0; 26];
+    for c in text.chars().filter_map(fold) {
+        counts[(c as u8 - b'a') as usize] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score() {
+        let english = "the quick brown fox jumps over the lazy dog and then runs into the forest";
+        let garbage = "qzx jvk wqz xjq zzvk qxj wvz kjq xzq";
+        assert!(Language::English.score(english) < Language::English.score(garbage));
+        assert_eq!(Language::English.score("123"), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_letter_counts() {
+        let counts = letter_counts("Été, à la plage");
+        assert_eq!(counts[0], 3);
+        assert_eq!(counts[4], 3);
+    }
+
+}
@@ -1,30 +1,387 @@
-use rand::Rng;
+// Linear feedback shift registers and the stream cipher built on them
 
-type Lsfr = Vec<u8>;
+use crate::base_cryptography::{Key, Cipher};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::registry::CipherDescriptor;
+use crate::stream;
+
+/// The longest register, its state being a `u64`.
+pub const MAX_LENGTH: usize = 64;
+
+/// `show key` steps through the whole period of registers up to this length only.
+const PERIOD_LENGTH: usize = 24;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "lfsr",
+    aliases: &["stream"],
+    key_type: "seed [taps]",
+    description: "XOR the bytes of the message with the output of a LFSR, in hex",
+    build: || Box::new(LfsrCipher {
+        seed: 0,
+        taps: vec![],
+        mode: Mode::Fibonacci,
+        message: "".to_string()
+    }),
+};
+
+/// How the feedback is wired.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Mode {
+    /// The taps are XORed together into the new bit.
+    Fibonacci,
+    /// The output bit is XORed into every tap.
+    Galois,
+}
+
+/// A register of up to 64 bits with the connection polynomial
+/// `1 + c1 x + ... + cL x^L`, given by the exponents of its non-zero terms.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Lfsr {
+    length: usize,
+    taps: Vec<usize>,
+    mask: u64,
+    state: u64,
+    mode: Mode,
+}
 
 fn xor(b1: u8, b2: u8) -> u8 {
-    match (b1, b2) {
-        (0, 0) => 0,
-        (1, 0) => 1,
-        (0, 1) => 1,
-        (1, 1) => 1,
-        _ => panic!()
+    b1 ^ b2
+}
+
+impl Lfsr {
+    /// A register of `length` bits; `seed` gives the initial state, the
+    /// first output bit being its least significant bit.
+    pub fn new(length: usize, taps: &[usize], seed: u64, mode: Mode) -> Result<Lfsr, CryptoError> {
+        if length == 0 || length > MAX_LENGTH {
+            return Err(CryptoError::InvalidKey(format!("a register of {} bits is not supported", length)));
+        }
+        if taps.is_empty() || taps.iter().any(|t| *t == 0 || *t > length) {
+            return Err(CryptoError::InvalidKey(format!("the taps {:?} don't fit in {} bits", taps, length)));
+        }
+        let full = if length == 64 { u64::MAX } else { (1 << length) - 1 };
+        if seed & full == 0 {
+            return Err(CryptoError::InvalidKey("the seed can't be zero".to_string()));
+        }
+        let mask = taps.iter()
+            .map(|t| match mode {
+                Mode::Fibonacci => 1 << (length - t),
+                Mode::Galois => 1 << (t - 1),
+            })
+            .fold(0, |acc, b| acc | b);
+        let mut taps = taps.to_vec();
+        taps.sort_by(|a, b| b.cmp(a));
+        taps.dedup();
+        Ok(Lfsr { length, taps, mask, state: seed & full, mode })
+    }
+
+    /// The register of degree `max(taps)`.
+    pub fn from_taps(taps: &[usize], seed: u64, mode: Mode) -> Result<Lfsr, CryptoError> {
+        Lfsr::new(taps.iter().copied().max().unwrap_or(0), taps, seed, mode)
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn taps(&self) -> &[usize] {
+        &self.taps
+    }
+
+    pub fn state(&self) -> u64 {
+        self.state
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Output one bit and shift the register.
+    pub fn step(&mut self) -> u8 {
+        let out = (self.state & 1) as u8;
+        match self.mode {
+            Mode::Fibonacci => {
+                let feedback = self.taps.iter()
+                    .map(|t| ((self.state >> (self.length - t)) & 1) as u8)
+                    .fold(0, xor);
+                self.state = (self.state >> 1) | ((feedback as u64) << (self.length - 1));
+            },
+            Mode::Galois => {
+                self.state >>= 1;
+                if out == 1 {
+                    self.state ^= self.mask;
+                }
+            }
+        }
+        out
+    }
+
+    /// The next eight bits, the first one being the most significant.
+    pub fn byte(&mut self) -> u8 {
+        (0..8).fold(0, |acc, _| (acc << 1) | self.step())
+    }
+
+    pub fn bytes(&mut self) -> impl Iterator<Item = u8> + '_ {
+        std::iter::repeat_with(move || self.byte())
+    }
+
+    /// Number of steps before the register comes back to its current state,
+    /// or `None` if it never does within `2^length` steps.
+    pub fn period(&self) -> Option<u64> {
+        let mut lfsr = self.clone();
+        let limit = if self.length >= 63 { u64::MAX } else { 1 << self.length };
+        let mut steps = 0;
+        loop {
+            lfsr.step();
+            steps += 1;
+            if lfsr.state == self.state {
+                return Some(steps);
+            }
+            if steps >= limit {
+                return None;
+            }
+        }
+    }
+
+    pub fn polynomial(&self) -> String {
+        self.taps.iter()
+            .map(|t| if *t == 1 { "x".to_string() } else { format!("x^{}", t) })
+            .chain(std::iter::once("1".to_string()))
+            .collect::<Vec<String>>()
+            .join(" + ")
+    }
+}
+
+impl Iterator for Lfsr {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> {
+        Some(self.step())
+    }
+}
+
+/// A seed and the exponents of a connection polynomial.
+pub type Register = (u64, Vec<usize>);
+
+impl Key for Register {
+    fn parse(value: &KeyValue) -> Option<Register> {
+        match value {
+            KeyValue::Seeded(seed, taps) if taps.iter().all(|t| *t > 0) =>
+                Some((*seed, taps.iter().map(|t| *t as usize).collect())),
+            _ => None
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{:#x} [{}]", self.0, self.1.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(","))
+    }
+}
+
+/// Stream cipher XORing the message with the bits of one register.
+#[derive(PartialEq, Debug, Clone)]
+pub struct LfsrCipher {
+    pub seed: u64,
+    pub taps: Vec<usize>,
+    pub mode: Mode,
+    pub message: String
+}
+
+impl LfsrCipher {
+    fn lfsr(&self) -> Result<Lfsr, CryptoError> {
+        if self.taps.is_empty() {
+            return Err(CryptoError::EmptyKey);
+        }
+        Lfsr::from_taps(&self.taps, self.seed, self.mode)
+    }
+}
+
+impl Cipher for LfsrCipher {
+    fn name(&self) -> &'static str {
+        "lfsr"
+    }
+
+    fn title(&self) -> &'static str {
+        "LFSR"
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        Ok(stream::encrypt(msg, self.lfsr()?))
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        stream::decrypt(ct, self.lfsr()?)
+    }
+
+    fn keystream(&self) -> Result<Box<dyn Iterator<Item = u8>>, CryptoError> {
+        Ok(Box::new(self.lfsr()?))
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let (seed, taps) = Register::from_key(self.name(), key)?;
+        let cipher = LfsrCipher { seed, taps, ..self.clone() };
+        cipher.lfsr()?;
+        Ok(Box::new(cipher))
+    }
+
+    fn describe(&self) -> String {
+        format!("{} ({:?})", (self.seed, self.taps.clone()).describe(), self.mode)
+    }
+
+    fn show_key(&self) -> String {
+        match self.lfsr() {
+            Ok(lfsr) => format!("{}\nstate: {:0width$b}\nperiod: {}",
+                lfsr.polynomial(), lfsr.state(),
+                if lfsr.length() <= PERIOD_LENGTH {
+                    lfsr.period().map(|p| p.to_string()).unwrap_or("-".to_string())
+                } else {
+                    format!("2^{}-1 if the polynomial is primitive", lfsr.length())
+                },
+                width = lfsr.length()),
+            Err(e) => e.to_string()
+        }
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(LfsrCipher {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn set_option(&self, option: &str, value: &str) -> Result<Box<dyn Cipher>, CryptoError> {
+        match (option, value) {
+            ("mode", "fibonacci") => Ok(Box::new(LfsrCipher { mode: Mode::Fibonacci, ..self.clone() })),
+            ("mode", "galois") => Ok(Box::new(LfsrCipher { mode: Mode::Galois, ..self.clone() })),
+            _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+        }
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        let mode = match self.mode {
+            Mode::Fibonacci => "fibonacci",
+            Mode::Galois => "galois"
+        };
+        vec![("mode", mode.to_string())]
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
     }
 }
 
-impl Vec<u8> {
-    fn new(size: i8) -> Vec<u8> {
-        (0..n).iter().map(|x| rand::thread_rng().gen_range(0..=1)).collect()
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xor() {
+        assert_eq!(xor(0, 0), 0);
+        assert_eq!(xor(1, 0), 1);
+        assert_eq!(xor(0, 1), 1);
+        assert_eq!(xor(1, 1), 0);
+    }
+
+    #[test]
+    fn test_fibonacci_known_sequence() {
+        let mut lfsr = Lfsr::from_taps(&[16, 14, 13, 11], 0xACE1, Mode::Fibonacci).unwrap();
+        assert_eq!(lfsr.step(), 1);
+        assert_eq!(lfsr.state(), 0x5670);
+        assert_eq!(lfsr.period(), Some(65535));
+    }
+
+    #[test]
+    fn test_galois_known_sequence() {
+        let mut lfsr = Lfsr::from_taps(&[16, 14, 13, 11], 0xACE1, Mode::Galois).unwrap();
+        assert_eq!(lfsr.step(), 1);
+        assert_eq!(lfsr.state(), 0xACE1 >> 1 ^ 0xB400);
+        assert_eq!(lfsr.period(), Some(65535));
+    }
+
+    #[test]
+    fn test_small_sequence() {
+        let lfsr = Lfsr::from_taps(&[4, 3], 0b0001, Mode::Fibonacci).unwrap();
+        assert_eq!(
+            lfsr.take(15).collect::<Vec<u8>>(),
+            vec![1, 0, 0, 0, 1, 0, 0, 1, 1, 0, 1, 0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_same_recurrence() {
+        let taps = [5, 3];
+        for mode in [Mode::Fibonacci, Mode::Galois] {
+            let bits = Lfsr::from_taps(&taps, 0b10110, mode).unwrap().take(100).collect::<Vec<u8>>();
+            for n in 0..95 {
+                assert_eq!(bits[n + 5], bits[n + 5 - 5] ^ bits[n + 5 - 3], "{:?} {}", mode, n);
+            }
+        }
     }
 
-    fn predictible() -> Vec<u8> {
-        [1, 0, 0, 1].iter().collect()
+    #[test]
+    fn test_show_key_long_register() {
+        let cipher = (DESCRIPTOR.build)().set_key(&KeyValue::Seeded(1, vec![64, 63, 61, 60])).unwrap();
+        assert!(cipher.show_key().ends_with("period: 2^64-1 if the polynomial is primitive"), "{}", cipher.show_key());
     }
 
-    fn generate(&self) -> (Vec<u8>, u8) {
-        let random_number = self[ self.len()-1 ];
-        let new_bit = xor(self[self.len()-1], self[self.len()-2]);
-        ([new_bit].iter().chain(self.iter()).take(self.len()).collect(),
-        random_number)
+    #[test]
+    fn test_primitive_periods() {
+        let primitive: [&[usize]; 6] = [&[2, 1], &[3, 2], &[4, 3], &[5, 3], &[7, 6], &[8, 6, 5, 4]];
+        for taps in primitive {
+            for mode in [Mode::Fibonacci, Mode::Galois] {
+                let lfsr = Lfsr::from_taps(taps, 1, mode).unwrap();
+                assert_eq!(lfsr.period(), Some((1 << lfsr.length()) - 1), "{} {:?}", lfsr.polynomial(), mode);
+            }
+        }
+        let not_primitive = Lfsr::from_taps(&[4, 2], 1, Mode::Fibonacci).unwrap();
+        assert!(not_primitive.period().unwrap() < 15);
     }
+
+    #[test]
+    fn test_bytes() {
+        let mut lfsr = Lfsr::from_taps(&[4, 3], 0b0001, Mode::Fibonacci).unwrap();
+        assert_eq!(lfsr.bytes().take(2).collect::<Vec<u8>>(), vec![0b10001001, 0b10101111]);
+    }
+
+    fn cipher(message: &str) -> LfsrCipher {
+        LfsrCipher {
+            seed: 0xACE1,
+            taps: vec![16, 14, 13, 11],
+            mode: Mode::Fibonacci,
+            message: message.to_string()
+        }
+    }
+
+    #[test]
+    fn test_lfsr_cipher() {
+        let c = cipher("Hello, world!");
+        let ct = c.encrypt2().unwrap();
+        assert_eq!(ct.len(), 26);
+        assert!(ct.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(c.decrypt(&ct), Ok("Hello, world!".to_string()));
+        let mut lfsr = Lfsr::from_taps(&[16, 14, 13, 11], 0xACE1, Mode::Fibonacci).unwrap();
+        assert_eq!(ct[..2], format!("{:02x}", b'H' ^ lfsr.byte()));
+    }
+
+    #[test]
+    fn test_lfsr_cipher_key() {
+        let c = cipher("");
+        let keyed = c.set_key(&KeyValue::Seeded(5, vec![4, 3])).unwrap();
+        assert_eq!(keyed.describe(), "0x5 [4,3] (Fibonacci)");
+        assert!(c.set_key(&KeyValue::Seeded(0, vec![4, 3])).is_err());
+        assert!(c.set_key(&KeyValue::Integer(3)).is_err());
+        let galois = c.set_option("mode", "galois").unwrap();
+        assert_ne!(galois.encrypt("abc"), c.encrypt("abc"));
+        assert_eq!(galois.decrypt(&galois.encrypt("abc").unwrap()), Ok("abc".to_string()));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Lfsr::from_taps(&[4, 3], 0, Mode::Fibonacci).is_err());
+        assert!(Lfsr::new(3, &[4], 1, Mode::Fibonacci).is_err());
+        assert!(Lfsr::from_taps(&[65], 1, Mode::Fibonacci).is_err());
+        assert_eq!(Lfsr::from_taps(&[4, 1], 1, Mode::Galois).unwrap().polynomial(), "x^4 + x + 1");
+    }
+
 }
@@ -0,0 +1,35 @@
+//! Classical cryptography toolbox.
+//!
+//! The ciphers implement [`Cipher`], are listed in [`registry::CIPHERS`] and
+//! are driven through [`Algo`]. [`interpreter::eval`] runs one line of the
+//! command language understood by [`parser::parse`].
+
+pub mod a5;
+pub mod affine;
+pub mod alphabet;
+pub mod analysis;
+pub mod base_cryptography;
+pub mod cesar;
+pub mod combiner;
+pub mod cryptanalysis;
+pub mod error;
+pub mod hill;
+pub mod interpreter;
+pub mod language;
+pub mod lfsr;
+pub mod parser;
+pub mod playfair;
+pub mod policy;
+pub mod randomness;
+pub mod registry;
+pub mod script;
+pub mod session;
+pub mod stream;
+pub mod tools;
+pub mod vigenere;
+
+pub use alphabet::Alphabet;
+pub use base_cryptography::{Algo, Cipher, Key};
+pub use error::CryptoError;
+pub use parser::commands::{Command, KeyValue};
+pub use session::Session;
@@ -1,65 +1,84 @@
-mod cesar;
-mod vigenere;
-mod parser;
-mod base_cryptography;
-mod tools;
+mod cli;
+mod completion;
 
+use std::path::PathBuf;
+use std::sync::Arc;
 use linefeed::{Interface, ReadResult};
-use base_cryptography::Algo;
-use parser::{parse, commands::{Command, KeyValue}};
+use cryptography::{Algo, Session};
+use cli::Mode;
+use completion::CommandCompleter;
+use cryptography::interpreter::eval;
+use cryptography::parser::open_heredoc;
 
 //type Reader = Interface<linefeed::DefaultTerminal> ;
 
-fn select_algo(algo: &str, context: &Algo) -> (Algo, String) {
-    match algo {
-        "cesar" => (Algo::new("cesar"), "Cesar algorithm selected.".to_string()),
-        "vigenere" => (Algo::new("vigenere"), "Vigenère algorithm selected.".to_string()),
-        _ => (context.clone(), " ".to_string())
-    }
-}
-
-fn help_message(_rest: &str, context: &Algo) -> (Algo, String) {
-    let help = format!("select: select an algo\nencrypt\t key message status exit help");
-   (context.clone(), help) 
-}
-
-fn eval(input: &str, context: &Algo) -> (Algo, String) {
-   match parse(input) {
-       Command::Exit => (Algo::Exit, "exit".to_string()),
-       Command::Select(algo) => select_algo(&algo, context),
-       Command::Encrypt(None) => (context.clone(), context.encrypt2()),
-       Command::Encrypt(Some((keyvalue, m))) => (context.clone(), context.encrypt(&m, keyvalue)),
-       Command::Decrypt(None) => (context.clone(), context.decrypt2()),
-       Command::Decrypt(Some((keyvalue, m))) => (context.clone(), context.decrypt(&m, keyvalue)),
-       Command::Key(k) => (context.set_key(k), "Key value set".to_string()),
-       Command::Message(m) => (context.set_message(&m), "Message set".to_string()),
-       Command::Status => (context.clone(), context.get_status()),
-       Command::Help => help_message("", context),
-       _ => (context.clone(), "".to_string())
-   }
+/// The history is kept in `~/.cryptography_history` from one run to the next.
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cryptography_history"))
 }
 
-fn initialize() -> (Interface<linefeed::DefaultTerminal>, Algo){
+fn initialize() -> (Interface<linefeed::DefaultTerminal>, Session){
     let reader = Interface::new("Cryptography").unwrap();
     reader.set_prompt(":c:> ").unwrap();
-    (reader, Algo::Empty)
+    reader.set_completer(Arc::new(CommandCompleter));
+    if let Some(path) = history_file() {
+        // The file doesn't exist on the first run.
+        let _ = reader.load_history(path);
+    }
+    (reader, Session::new())
+}
+
+fn save_history(reader: &Interface<linefeed::DefaultTerminal>) {
+    if let Some(path) = history_file() {
+        if let Err(e) = reader.save_history(&path) {
+            eprintln!("error: can't save the history to {}: {}", path.display(), e);
+        }
+    }
 }
 
 
 fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    match cli::parse_args(&args) {
+        Ok(Mode::Interactive) => interactive(),
+        Ok(mode) => if let Err(e) = cli::run(&mode) {
+            eprintln!("error: {}", e);
+            std::process::exit(1);
+        },
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+fn interactive() {
     let (reader, mut context) = initialize();
-    let mut res = "".to_string();
-    println!("res: {:?}", res);
 
-    while let ReadResult::Input(input) = reader.read_line().unwrap() {
-        (context, res) = eval(&input, &context);
-        println!("{:?}", res);
-        if context == Algo::Exit {
+    while let ReadResult::Input(mut input) = reader.read_line().unwrap() {
+        while let Some(word) = open_heredoc(&input) {
+            reader.set_prompt(&format!("{}> ", word)).unwrap();
+            match reader.read_line().unwrap() {
+                ReadResult::Input(line) => input = format!("{}\n{}", input, line),
+                _ => break
+            }
+        }
+        if !input.trim().is_empty() {
+            reader.add_history_unique(input.clone());
+        }
+        let (next, output) = eval(&input, &context);
+        context = next;
+        match output {
+            Ok(res) => println!("{}", res),
+            Err(e) => println!("error: {}", e)
+        }
+        if let Algo::Exit = context.algo {
+            save_history(&reader);
             std::process::exit(0);
         } else {
-            reader.set_prompt(&context.get_prompt()).unwrap();
+            reader.set_prompt(&context.algo.get_prompt()).unwrap();
         }
     }
+    save_history(&reader);
     println!("Goodbye.");
 }
-
@@ -1,48 +1,152 @@
-use crate::Algo;
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::Algo;
+use crate::policy::Policy;
 
-#[derive(PartialEq, Debug)]
+/// A command of the REPL.
+#[derive(PartialEq, Debug, Clone)]
 pub enum Command {
    Select(String),
    Encrypt(Option<(KeyValue, String)>),
    Decrypt(Option<(KeyValue, String)>),
    Key(KeyValue),
    Message(String),
+   Alphabet(Option<Alphabet>),
+   Set(String, String),
+   ShowKey,
+   Analyze(Option<String>),
+   Crack(Option<String>, Option<String>),
+   Berlekamp(String, Option<String>),
+   Randomness(Bits),
+   SaveSession(String),
+   LoadSession(String),
+   Source(String, OnError),
    Status,
    Exit,
-   Help,
+   Help(Option<String>),
    Empty,
 }
 
+/// Where the bits of the randomness tests come from.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Bits {
+    /// The first bits of the keystream of the current cipher.
+    Keystream(usize),
+    File(String),
+}
+
+/// What a script does when one of its commands fails.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum OnError {
+    Stop,
+    Continue,
+}
+
+/// A key as written by the user, before a cipher interprets it.
 #[derive(PartialEq, Debug, Clone)]
 pub enum KeyValue {
-    Integer(i8),
-    Range(i8, i8),
+    Integer(i64),
+    Range(i64, i64),
+    Pair(i64, i64),
+    Matrix(Vec<Vec<i64>>),
+    Seeded(u64, Vec<i64>),
+    Registers(Vec<(u64, Vec<i64>)>),
+    Framed(u64, u64),
     String(String),
     Empty
 }
 
+fn join<T, F: Fn(&T) -> String>(items: &[T], f: F) -> String {
+    items.iter().map(f).collect::<Vec<String>>().join(",")
+}
+
 impl KeyValue {
+    /// The key as typed in the `key` command.
+    pub fn syntax(&self) -> String {
+        match self {
+            KeyValue::Integer(i) => i.to_string(),
+            KeyValue::Range(b, e) => format!("{}..{}", b, e),
+            KeyValue::Pair(a, b) => format!("{},{}", a, b),
+            KeyValue::Matrix(m) => format!("[{}]", join(m, |row| format!("[{}]", join(row, i64::to_string)))),
+            KeyValue::Seeded(seed, taps) => format!("{:#x} [{}]", seed, join(taps, i64::to_string)),
+            KeyValue::Registers(r) => r.iter()
+                .map(|(seed, taps)| KeyValue::Seeded(*seed, taps.clone()).syntax())
+                .collect::<Vec<String>>()
+                .join(", "),
+            KeyValue::Framed(key, frame) => format!("{:#x} frame {:#x}", key, frame),
+            KeyValue::String(s) => format!("{:?}", s),
+            KeyValue::Empty => "".to_string(),
+        }
+    }
+
     pub fn describe(&self) -> String {
         match self {
             KeyValue::Integer(i) => format!("{} [integer]", i),
             KeyValue::Range(b, e) => format!("{}..{} [range]", b, e),
-            KeyValue::String(s) => format!("\"{}\" [string]", s),
-            KeyValue::Empty => format!("Empty [empty]"),
+            KeyValue::Pair(a, b) => format!("{},{} [pair]", a, b),
+            KeyValue::Matrix(m) => format!("{:?} [matrix]", m),
+            KeyValue::Seeded(seed, taps) => format!("{:#x} {:?} [seed+taps]", seed, taps),
+            KeyValue::Framed(key, frame) => format!("{:#x} frame {:#x} [key+frame]", key, frame),
+            KeyValue::Registers(r) => format!("{} [registers]", r.iter()
+                .map(|(seed, taps)| format!("{:#x} {:?}", seed, taps))
+                .collect::<Vec<String>>()
+                .join(", ")),
+            KeyValue::String(s) => format!("{:?} [string]", s),
+            KeyValue::Empty => "Empty [empty]".to_string(),
         }
     }
 }
 
 impl Command {
-    fn describe(&self) -> String {
+    /// The words a command starts with.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Command::Select(_) => "select",
+            Command::Encrypt(_) => "encrypt",
+            Command::Decrypt(_) => "decrypt",
+            Command::Key(_) => "key",
+            Command::Message(_) => "message",
+            Command::Alphabet(_) => "alphabet",
+            Command::Set(_, _) => "set",
+            Command::ShowKey => "show key",
+            Command::Analyze(_) => "analyze",
+            Command::Crack(_, _) => "crack",
+            Command::Berlekamp(_, _) => "berlekamp",
+            Command::Randomness(_) => "randomness",
+            Command::SaveSession(_) => "save session",
+            Command::LoadSession(_) => "load session",
+            Command::Source(_, _) => "source",
+            Command::Status => "status",
+            Command::Exit => "exit",
+            Command::Help(_) => "help",
+            Command::Empty => ""
+        }
+    }
+
+    /// Every command, starting from [`Command::Empty`].
+    pub fn all() -> impl Iterator<Item = Command> {
+        Command::Empty
+    }
+
+    pub fn describe(&self) -> String {
         match &self {
             Command::Select(_) => format!("Select an algo [{}]", Algo::list_algo()),
             Command::Encrypt(_) => "Encrypt according to the given algorithm.\noptional: [key] [message]".to_string(),
-            Command::Decrypt(_) => "Decrypt according to the given algorithm.\noptional: [key] [message]".to_string(),
-            Command::Key(_) => "[number] set the key".to_string(),
-            Command::Message(_) => "[string] set the message".to_string(),
+            Command::Decrypt(_) => "Decrypt according to the given algorithm.\noptional: [key] [message]\na range key (0..25) tries every key and ranks the results".to_string(),
+            Command::Key(_) => "[number|string|number,number|[[number,..],..]|seed [taps], ..|key frame number] set the key".to_string(),
+            Command::Message(_) => "[string] set the message.\na string is quoted with \" or ', with escapes such as \\n, \\x41 or \\u{e9},\nor is a heredoc: <<END, then lines up to one starting with END".to_string(),
+            Command::Alphabet(_) => format!("[{}|string] set the alphabet, or show it", Alphabet::names().join("|")),
+            Command::Set(_, _) => format!("set an option: {}", Policy::options().join(", ")),
+            Command::Analyze(_) => "Frequency analysis of the message.\noptional: [message]".to_string(),
+            Command::Crack(_, _) => "Recover the key and the message without the key (vigenere).\noptional: [message] [with known plaintext] (geffe)".to_string(),
+            Command::Berlekamp(_, _) => "Find the shortest LFSR generating a keystream and predict what follows.\n[bits] or [hex ciphertext] with [plaintext]".to_string(),
+            Command::Randomness(_) => "Run statistical tests (NIST SP 800-22) on the keystream of the current cipher.\noptional: [number of bits] or file [path]".to_string(),
+            Command::SaveSession(_) => "[file] write the algorithm, the key, the message, the alphabet, the options and the last result to a file".to_string(),
+            Command::LoadSession(_) => "[file] restore a session written by save session".to_string(),
+            Command::Source(_, _) => "[file] run the commands of a file, one per line, showing each with its result.\noptional: [continue] after an error".to_string(),
+            Command::ShowKey => "Show the key of the current algorithm, as a table when it has one".to_string(),
             Command::Status => "Get the status of the current algorithm".to_string(),
             Command::Exit => "Quit the application".to_string(),
-            Command::Help => "Display the help message".to_string(),
+            Command::Help(_) => "Display the help message.\noptional: [command]".to_string(),
             Command::Empty => "".to_string()
         }
     }
@@ -51,16 +155,45 @@ impl Command {
 impl Iterator for Command {
     type Item = Command;
     fn next(&mut self) -> Option<Command> {
-        match self {
+        let next = match self {
             Command::Empty => Some(Command::Select("".to_string())),
             Command::Select(_) => Some(Command::Encrypt(None)),
             Command::Encrypt(_) => Some(Command::Decrypt(None)),
             Command::Decrypt(_) => Some(Command::Key(KeyValue::Empty)),
             Command::Key(_) => Some(Command::Message("".to_string())),
-            Command::Message(_) => Some(Command::Status),
+            Command::Message(_) => Some(Command::Alphabet(None)),
+            Command::Alphabet(_) => Some(Command::Set("".to_string(), "".to_string())),
+            Command::Set(_, _) => Some(Command::ShowKey),
+            Command::ShowKey => Some(Command::Analyze(None)),
+            Command::Analyze(_) => Some(Command::Crack(None, None)),
+            Command::Crack(_, _) => Some(Command::Berlekamp("".to_string(), None)),
+            Command::Berlekamp(_, _) => Some(Command::Randomness(Bits::Keystream(0))),
+            Command::Randomness(_) => Some(Command::SaveSession("".to_string())),
+            Command::SaveSession(_) => Some(Command::LoadSession("".to_string())),
+            Command::LoadSession(_) => Some(Command::Source("".to_string(), OnError::Stop)),
+            Command::Source(_, _) => Some(Command::Status),
             Command::Status => Some(Command::Exit),
-            Command::Exit => Some(Command::Help),
-            Command::Help => None
+            Command::Exit => Some(Command::Help(None)),
+            Command::Help(_) => None
+        };
+        if let Some(command) = &next {
+            *self = command.clone();
         }
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all() {
+        let keywords = Command::all().map(|c| c.keyword()).collect::<Vec<&str>>();
+        assert_eq!(keywords.len(), 18);
+        assert_eq!(keywords[0], "select");
+        assert_eq!(keywords[17], "help");
+        assert!(Command::all().all(|c| !c.describe().is_empty()));
     }
+
 }
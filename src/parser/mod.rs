@@ -7,20 +7,30 @@ use nom::IResult;
 use nom::sequence::preceded;
 use nom::bytes::complete::tag;
 use nom::character::complete::alphanumeric1;
-use nom::multi::many1;
+use nom::multi::{many1, separated_list1};
 use nom::character::complete::space1;
 use nom::sequence::delimited;
 use nom::sequence::tuple;
-use nom::character::complete::digit1;
+use nom::character::complete::{digit1, hex_digit1};
 use nom::sequence::terminated;
 use nom::branch::alt;
+use nom::bytes::complete::take_while1;
+use nom::bytes::complete::take_while_m_n;
+use nom::character::complete::{char, line_ending, none_of, one_of};
+use nom::combinator::{cut, map, map_opt, map_res, opt, recognize, value};
+use nom::multi::fold_many0;
+use nom::sequence::pair;
+use nom::character::complete::space0;
+use nom::error::{Error, ErrorKind};
 use commands::Command;
-use crate::KeyValue;
+use commands::{Bits, KeyValue, OnError};
+use crate::alphabet::Alphabet;
+use crate::error::CryptoError;
 
 fn parse_select(s: &str) -> IResult<&str,Command> {
     let res = preceded(
         tag("select "),
-        alphanumeric1
+        cut(alphanumeric1)
           )(s);
     match res {
         Ok((s, name)) => Ok((s, Command::Select(name.to_string()))),
@@ -39,16 +49,96 @@ fn parse_words_and_spaces(s: &str) -> IResult<&str, String> {
     }
 }
 
+fn hex_char(hex: &str) -> Option<char> {
+    u32::from_str_radix(hex, 16).ok().and_then(char::from_u32)
+}
+
+/// The character written after a backslash in a quoted text.
+fn parse_escape(s: &str) -> IResult<&str,char> {
+    alt((
+        map_opt(preceded(char('x'), take_while_m_n(2, 2, |c: char| c.is_ascii_hexdigit())), hex_char),
+        map_opt(delimited(tag("u{"), take_while_m_n(1, 6, |c: char| c.is_ascii_hexdigit()), char('}')), hex_char),
+        value('\n', char('n')),
+        value('\r', char('r')),
+        value('\t', char('t')),
+        value('\0', char('0')),
+        one_of("\\\"'")))(s)
+}
+
+/// A text between `quote`s, where a backslash starts an escape sequence.
+fn parse_quoted(quote: char) -> impl FnMut(&str) -> IResult<&str,String> {
+    let plain = if quote == '"' { "\"\\" } else { "'\\" };
+    move |s| delimited(
+        char(quote),
+        fold_many0(
+            alt((preceded(char('\\'), cut(parse_escape)), none_of(plain))),
+            String::new,
+            |mut text, c| { text.push(c); text }),
+        cut(char(quote)))(s)
+}
+
+/// `<<END`, then the lines of the text up to a line starting with `END`.
+fn parse_heredoc(s: &str) -> IResult<&str,String> {
+    let (body, word) = preceded(tag("<<"), alphanumeric1)(s)?;
+    let (body, _) = cut(line_ending)(body)?;
+    let mut start = 0;
+    for line in body.split('\n') {
+        if let Some(rest) = line.strip_prefix(word) {
+            if rest.is_empty() || rest.starts_with(' ') {
+                let text = body[..start].trim_end_matches('\n').trim_end_matches('\r');
+                return Ok((&body[start + word.len()..], text.to_string()));
+            }
+        }
+        start += line.len() + 1;
+    }
+    Err(nom::Err::Failure(Error::new(s, ErrorKind::TakeUntil)))
+}
+
 fn parse_text(s: &str) -> IResult<&str,String> {
-    delimited(
-        tag("\""),
-        parse_words_and_spaces,
-        tag("\""))(s)
+    alt((parse_quoted('"'), parse_quoted('\''), parse_heredoc))(s)
+}
+
+/// The word that closes a heredoc opened but not closed in `input`: the REPL
+/// and the scripts read more lines until it comes.
+pub fn open_heredoc(input: &str) -> Option<String> {
+    let mut quote = None;
+    let mut chars = input.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => { chars.next(); },
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (None, '<') => match parse_heredoc(&input[i..]) {
+                Ok((rest, _)) => return open_heredoc(rest),
+                Err(nom::Err::Failure(_)) => {
+                    let word = input[i + 2..].chars().take_while(|c| c.is_alphanumeric()).collect::<String>();
+                    let after = &input[i + 2 + word.len()..];
+                    if after.is_empty() || after.starts_with('\n') || after.starts_with("\r\n") {
+                        return Some(word);
+                    }
+                },
+                Err(_) => {}
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse a text written as in the commands, quoted or as a heredoc.
+pub fn parse_text_literal(s: &str) -> Result<String, CryptoError> {
+    finish(s, parse_text(s))
+}
+
+/// What follows the text that `s` starts with, if it starts with a whole one.
+pub fn skip_text(s: &str) -> Option<&str> {
+    parse_text(s).ok().map(|(rest, _)| rest)
 }
 
 fn parse_numeric_key(s: &str) -> IResult<&str,KeyValue> {
-    match digit1(s) {
-        Ok((s, v)) => Ok((s, KeyValue::Integer(parse_digit(v)))),
+    match parse_digit(s) {
+        Ok((s, v)) => Ok((s, KeyValue::Integer(v))),
         Err(r) => Err(r)
     }
 }
@@ -62,29 +152,103 @@ fn parse_text_key(s: &str) -> IResult<&str,KeyValue> {
 
 fn parse_range_key(s: &str) -> IResult<&str,KeyValue> {
     let res = tuple((
-            digit1,
+            parse_digit,
             tag(".."),
-            digit1))(s);
+            cut(parse_digit)))(s);
+    match res {
+        Ok((s, (v1, t, v2))) => Ok((s, KeyValue::Range(v1, v2))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_pair_key(s: &str) -> IResult<&str,KeyValue> {
+    let res = tuple((
+            parse_digit,
+            delimited(space0, tag(","), space0),
+            cut(parse_digit)))(s);
+    match res {
+        Ok((s, (a, _, b))) => Ok((s, KeyValue::Pair(a, b))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_list<'a, O, F>(item: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+    where F: FnMut(&'a str) -> IResult<&'a str, O> {
+    delimited(
+        terminated(tag("["), space0),
+        separated_list1(delimited(space0, tag(","), space0), item),
+        preceded(space0, tag("]")))
+}
+
+fn parse_matrix_key(s: &str) -> IResult<&str,KeyValue> {
+    match parse_list(parse_list(parse_digit))(s) {
+        Ok((s, m)) => Ok((s, KeyValue::Matrix(m))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_seed(s: &str) -> IResult<&str,u64> {
+    let (rest, (prefix, v)) = alt((
+            pair(tag("0x"), hex_digit1),
+            pair(tag(""), digit1)))(s)?;
+    let radix = if prefix.is_empty() { 10 } else { 16 };
+    match u64::from_str_radix(v, radix) {
+        Ok(n) => Ok((rest, n)),
+        Err(_) => Err(nom::Err::Failure(Error::new(s, ErrorKind::MapRes)))
+    }
+}
+
+fn parse_register(s: &str) -> IResult<&str,(u64, Vec<i64>)> {
+    let res = tuple((
+            parse_seed,
+            space1,
+            parse_list(parse_digit)))(s);
+    match res {
+        Ok((s, (seed, _, taps))) => Ok((s, (seed, taps))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_seeded_key(s: &str) -> IResult<&str,KeyValue> {
+    match separated_list1(pair(tag(","), space0), parse_register)(s) {
+        Ok((s, mut r)) if r.len() == 1 => {
+            let (seed, taps) = r.remove(0);
+            Ok((s, KeyValue::Seeded(seed, taps)))
+        },
+        Ok((s, r)) => Ok((s, KeyValue::Registers(r))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_framed_key(s: &str) -> IResult<&str,KeyValue> {
+    let res = tuple((
+            parse_seed,
+            tag(" frame "),
+            cut(parse_seed)))(s);
     match res {
-        Ok((s, (v1, t, v2))) => Ok((s, KeyValue::Range(parse_digit(v1), parse_digit(v2)))),
+        Ok((s, (key, _, frame))) => Ok((s, KeyValue::Framed(key, frame))),
         Err(r) => Err(r)
     }
 }
 
 fn parse_key_value(s: &str) -> IResult<&str,KeyValue> {
     alt((
+            parse_matrix_key,
+            parse_framed_key,
+            parse_seeded_key,
+            parse_range_key,
+            parse_pair_key,
             parse_numeric_key,
-            parse_text_key,
-            parse_range_key
+            parse_text_key
         ))(s)
 }
 
 fn parse_encrypt_full(s: &str) -> IResult<&str,Command> {
     let res = tuple((
         tag("encrypt "),
-        parse_text,
-        tag(" with "),
-        parse_key_value,
+        cut(parse_text),
+        cut(tag(" with ")),
+        cut(parse_key_value),
         ))(s);
     match res {
         Ok((s, (e, m, w, k))) => Ok((s, Command::Encrypt(Some((k, m))))),
@@ -110,9 +274,9 @@ fn parse_encrypt(s: &str) -> IResult<&str,Command> {
 fn parse_decrypt_full(s: &str) -> IResult<&str,Command> {
     let res = tuple((
         tag("decrypt "),
-        parse_text,
-        tag(" with "),
-        parse_key_value,
+        cut(parse_text),
+        cut(tag(" with ")),
+        cut(parse_key_value),
         ))(s);
     match res {
         Ok((s, (e, m, w, k))) => Ok((s, Command::Decrypt(Some((k, m))))),
@@ -135,17 +299,22 @@ fn parse_decrypt(s: &str) -> IResult<&str,Command> {
         ))(s)
 }
 
-fn parse_digit(s: &str) -> i8 {
-    match s.parse::<i8>() {
-        Ok(v) => v,
-        _ => 0_i8
+fn parse_number(s: &str) -> IResult<&str,&str> {
+    recognize(pair(opt(tag("-")), digit1))(s)
+}
+
+fn parse_digit(s: &str) -> IResult<&str,i64> {
+    let (rest, v) = parse_number(s)?;
+    match v.parse::<i64>() {
+        Ok(n) => Ok((rest, n)),
+        Err(_) => Err(nom::Err::Failure(Error::new(s, ErrorKind::MapRes)))
     }
 }
 
 fn parse_key(s: &str) -> IResult<&str,Command> {
     let res = preceded(
                 tag("key "),
-                parse_key_value)(s);
+                cut(parse_key_value))(s);
     match res {
         Ok((s, k)) => Ok((s, Command::Key(k))),
         Err(r) => Err(r)
@@ -155,13 +324,140 @@ fn parse_key(s: &str) -> IResult<&str,Command> {
 fn parse_message(s: &str) -> IResult<&str,Command> {
     let res = preceded(
             tag("message "),
-            parse_text)(s);
+            cut(parse_text))(s);
     match res {
         Ok((s, t)) => Ok((s, Command::Message(t))),
         Err(r) => Err(r)
     }
 }
 
+fn parse_alphabet_name(s: &str) -> IResult<&str,Alphabet> {
+    let (rest, name) = alphanumeric1(s)?;
+    match Alphabet::named(name) {
+        Some(a) => Ok((rest, a)),
+        None => Err(nom::Err::Failure(Error::new(s, ErrorKind::Tag)))
+    }
+}
+
+fn parse_alphabet_custom(s: &str) -> IResult<&str,Alphabet> {
+    let (rest, letters) = parse_text(s)?;
+    match Alphabet::custom(&letters) {
+        Ok(a) => Ok((rest, a)),
+        Err(_) => Err(nom::Err::Failure(Error::new(s, ErrorKind::Verify)))
+    }
+}
+
+fn parse_alphabet(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("alphabet"),
+            opt(preceded(
+                    space1,
+                    cut(alt((parse_alphabet_name, parse_alphabet_custom))))))(s);
+    match res {
+        Ok((s, a)) => Ok((s, Command::Alphabet(a))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_set(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("set "),
+            cut(tuple((alphanumeric1, space1, alphanumeric1))))(s);
+    match res {
+        Ok((s, (o, _, v))) => Ok((s, Command::Set(o.to_string(), v.to_string()))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_show_key(s: &str) -> IResult<&str,Command> {
+    match tag("show key")(s) {
+        Ok((s, _)) => Ok((s, Command::ShowKey)),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_analyze(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("analyze"),
+            opt(preceded(space1, cut(parse_text))))(s);
+    match res {
+        Ok((s, t)) => Ok((s, Command::Analyze(t))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_crack(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("crack"),
+            opt(preceded(space1, cut(pair(
+                parse_text,
+                opt(preceded(tag(" with "), cut(parse_text))))))))(s);
+    match res {
+        Ok((s, Some((t, k)))) => Ok((s, Command::Crack(Some(t), k))),
+        Ok((s, None)) => Ok((s, Command::Crack(None, None))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_berlekamp(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("berlekamp "),
+            cut(pair(
+                parse_text,
+                opt(preceded(tag(" with "), cut(parse_text))))))(s);
+    match res {
+        Ok((s, (t, p))) => Ok((s, Command::Berlekamp(t, p))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_path(s: &str) -> IResult<&str,String> {
+    match take_while1(|c: char| !c.is_whitespace() && c != '"')(s) {
+        Ok((s, p)) => Ok((s, p.to_string())),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_bits(s: &str) -> IResult<&str,Bits> {
+    alt((
+        map(preceded(tag("file "), cut(parse_path)), Bits::File),
+        map(map_res(digit1, |d: &str| d.parse::<usize>()), Bits::Keystream)))(s)
+}
+
+fn parse_randomness(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("randomness"),
+            opt(preceded(space1, cut(parse_bits))))(s);
+    match res {
+        Ok((s, b)) => Ok((s, Command::Randomness(b.unwrap_or(Bits::Keystream(100_000))))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_save_session(s: &str) -> IResult<&str,Command> {
+    match preceded(tag("save session "), cut(parse_path))(s) {
+        Ok((s, p)) => Ok((s, Command::SaveSession(p))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_load_session(s: &str) -> IResult<&str,Command> {
+    match preceded(tag("load session "), cut(parse_path))(s) {
+        Ok((s, p)) => Ok((s, Command::LoadSession(p))),
+        Err(r) => Err(r)
+    }
+}
+
+fn parse_source(s: &str) -> IResult<&str,Command> {
+    let res = preceded(
+            tag("source "),
+            cut(pair(parse_path, opt(preceded(space1, tag("continue"))))))(s);
+    match res {
+        Ok((s, (p, c))) => Ok((s, Command::Source(p, if c.is_some() { OnError::Continue } else { OnError::Stop }))),
+        Err(r) => Err(r)
+    }
+}
+
 fn parse_status(s: &str) -> IResult<&str,Command> {
     match tag("status")(s) {
         Ok((s, _)) => Ok((s, Command::Status)),
@@ -177,26 +473,66 @@ fn parse_exit(s: &str) -> IResult<&str,Command> {
 }
 
 fn parse_help(s: &str) -> IResult<&str,Command> {
-    match tag("help")(s) {
-        Ok((s, _)) => Ok((s, Command::Help)),
+    let res = preceded(
+            tag("help"),
+            opt(preceded(space1, cut(parse_words_and_spaces))))(s);
+    match res {
+        Ok((s, c)) => Ok((s, Command::Help(c))),
         Err(r) => Err(r)
     }
 }
 
-pub fn parse(s: &str) -> Command {
-    let res = alt((
+fn parse_error(s: &str, rest: &str) -> CryptoError {
+    CryptoError::Parse {
+        position: s[..s.len() - rest.len()].chars().count(),
+        found: rest.split_whitespace().next().unwrap_or("").to_string()
+    }
+}
+
+/// Parse one command line; a blank line gives [`Command::Empty`].
+pub fn parse(s: &str) -> Result<Command, CryptoError> {
+    if s.trim().is_empty() {
+        return Ok(Command::Empty);
+    }
+    finish(s, terminated(alt((
         parse_select,
         parse_encrypt,
         parse_decrypt,
         parse_key,
         parse_message,
+        parse_alphabet,
+        parse_set,
+        parse_show_key,
+        parse_analyze,
+        parse_crack,
+        parse_berlekamp,
+        parse_randomness,
+        parse_save_session,
+        parse_load_session,
+        parse_source,
         parse_status,
         parse_exit,
         parse_help,
-          ))(s);
+          )), space0)(s))
+}
+
+/// Parse a key written as in the `key` command.
+pub fn parse_key_value_text(s: &str) -> Result<KeyValue, CryptoError> {
+    finish(s, terminated(parse_key_value, space0)(s))
+}
+
+/// Require the whole input to be consumed and turn nom errors into ours.
+fn finish<O>(s: &str, res: IResult<&str, O>) -> Result<O, CryptoError> {
     match res {
-        Ok((s, cmd)) => cmd,
-        Err(r) => Command::Empty
+        Ok(("", cmd)) => Ok(cmd),
+        Ok((rest, _)) => Err(parse_error(s, rest)),
+        Err(nom::Err::Error(e)) | Err(nom::Err::Failure(e)) => match e.code {
+            ErrorKind::MapRes => Err(CryptoError::KeyOutOfRange(parse_number(e.input)
+                .map(|(_, d)| d.to_string())
+                .unwrap_or_default())),
+            _ => Err(parse_error(s, e.input))
+        },
+        Err(nom::Err::Incomplete(_)) => Err(parse_error(s, ""))
     }
 }
 
@@ -217,12 +553,50 @@ mod tests {
             parse_words_and_spaces("hello world").unwrap().1,
             "hello world".to_string());
     }
+
+    #[test]
+    fn test_quoted_text() {
+        assert_eq!(
+            parse("message \"Don't panic, it's 4:20!\tOK?\""),
+            Ok(Command::Message("Don't panic, it's 4:20!\tOK?".to_string())));
+        assert_eq!(
+            parse_text_literal(r#""say \"hi\"\n\x41\u{e9}\\""#),
+            Ok("say \"hi\"\nAé\\".to_string()));
+        assert_eq!(
+            parse("encrypt 'the \"café\" isn\\'t open' with 3"),
+            Ok(Command::Encrypt(Some((KeyValue::Integer(3), "the \"café\" isn't open".to_string())))));
+        assert_eq!(parse_text_literal("\"\""), Ok("".to_string()));
+        assert!(parse_text_literal(r#""bad \q escape""#).is_err());
+        assert!(parse_text_literal(r#""\u{110000}""#).is_err());
+        assert!(parse("message \"unterminated").is_err());
+    }
+
+    #[test]
+    fn test_heredoc() {
+        assert_eq!(
+            parse("message <<END\nfirst line\n  second, indented\nEND"),
+            Ok(Command::Message("first line\n  second, indented".to_string())));
+        assert_eq!(
+            parse("encrypt <<EOF\nhello\nworld\nEOF with 3"),
+            Ok(Command::Encrypt(Some((KeyValue::Integer(3), "hello\nworld".to_string())))));
+        assert_eq!(parse("message <<END\nEND"), Ok(Command::Message("".to_string())));
+        assert!(parse("message <<END\nnever closed").is_err());
+    }
+
+    #[test]
+    fn test_open_heredoc() {
+        assert_eq!(open_heredoc("message <<END"), Some("END".to_string()));
+        assert_eq!(open_heredoc("message <<END\nline\nENDING"), Some("END".to_string()));
+        assert_eq!(open_heredoc("message <<END\nline\nEND"), None);
+        assert_eq!(open_heredoc("message \"<<END\""), None);
+        assert_eq!(open_heredoc("message \"a\""), None);
+    }
     
     #[test]
     fn test_parse_key() {
         assert_eq!(
             parse_key("key 67").unwrap().1,
-            Command::Key(KeyValue::Integer(67_i8)));
+            Command::Key(KeyValue::Integer(67_i64)));
     }
 
     #[test]
@@ -239,6 +613,50 @@ mod tests {
             Command::Message("Hello".to_string()));
     }
 
+    #[test]
+    fn test_alphabet() {
+        assert_eq!(
+            parse("alphabet french"),
+            Ok(Command::Alphabet(Some(Alphabet::french()))));
+        assert_eq!(
+            parse("alphabet \"xyz\""),
+            Ok(Command::Alphabet(Some(Alphabet::custom("xyz").unwrap()))));
+        assert_eq!(
+            parse("alphabet"),
+            Ok(Command::Alphabet(None)));
+        assert_eq!(
+            parse("alphabet klingon"),
+            Err(CryptoError::Parse { position: 9, found: "klingon".to_string() }));
+    }
+
+    #[test]
+    fn test_set() {
+        assert_eq!(
+            parse("set nonalpha strip"),
+            Ok(Command::Set("nonalpha".to_string(), "strip".to_string())));
+    }
+
+    #[test]
+    fn test_show_key() {
+        assert_eq!(parse("show key"), Ok(Command::ShowKey));
+    }
+
+    #[test]
+    fn test_analyze() {
+        assert_eq!(parse("analyze"), Ok(Command::Analyze(None)));
+        assert_eq!(
+            parse("analyze \"some text\""),
+            Ok(Command::Analyze(Some("some text".to_string()))));
+    }
+
+    #[test]
+    fn test_crack() {
+        assert_eq!(parse("crack"), Ok(Command::Crack(None, None)));
+        assert_eq!(
+            parse("crack \"ab01\" with \"known\""),
+            Ok(Command::Crack(Some("ab01".to_string()), Some("known".to_string()))));
+    }
+
     #[test]
     fn test_status() {
         assert_eq!(
@@ -257,7 +675,133 @@ mod tests {
     fn test_help() {
         assert_eq!(
             parse_help("help").unwrap().1,
-            Command::Help);
+            Command::Help(None));
+        assert_eq!(
+            parse("help show key"),
+            Ok(Command::Help(Some("show key".to_string()))));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse("key 3..12"),
+            Ok(Command::Key(KeyValue::Range(3, 12))));
+    }
+
+    #[test]
+    fn test_parse_pair() {
+        assert_eq!(
+            parse("key 5,8"),
+            Ok(Command::Key(KeyValue::Pair(5, 8))));
+        assert_eq!(
+            parse("encrypt \"hello\" with 5, -8"),
+            Ok(Command::Encrypt(Some((KeyValue::Pair(5, -8), "hello".to_string())))));
+    }
+
+    #[test]
+    fn test_parse_matrix() {
+        assert_eq!(
+            parse("key [[3,3],[2,5]]"),
+            Ok(Command::Key(KeyValue::Matrix(vec![vec![3, 3], vec![2, 5]]))));
+        assert_eq!(
+            parse("key [ [1, 2], [3, 4] ]"),
+            Ok(Command::Key(KeyValue::Matrix(vec![vec![1, 2], vec![3, 4]]))));
+    }
+
+    #[test]
+    fn test_parse_berlekamp() {
+        assert_eq!(
+            parse("berlekamp \"0110 1011\""),
+            Ok(Command::Berlekamp("0110 1011".to_string(), None)));
+        assert_eq!(
+            parse("berlekamp \"be\" with \"A\""),
+            Ok(Command::Berlekamp("be".to_string(), Some("A".to_string()))));
+    }
+
+    #[test]
+    fn test_parse_framed() {
+        assert_eq!(
+            parse("key 0x1223456789abcdef frame 0x134"),
+            Ok(Command::Key(KeyValue::Framed(0x1223456789abcdef, 0x134))));
+    }
+
+    #[test]
+    fn test_parse_randomness() {
+        assert_eq!(parse("randomness"), Ok(Command::Randomness(Bits::Keystream(100_000))));
+        assert_eq!(parse("randomness 5000"), Ok(Command::Randomness(Bits::Keystream(5000))));
+        assert_eq!(
+            parse("randomness file data/e.txt"),
+            Ok(Command::Randomness(Bits::File("data/e.txt".to_string()))));
+    }
+
+    #[test]
+    fn test_parse_session() {
+        assert_eq!(
+            parse("save session lab/monday.session"),
+            Ok(Command::SaveSession("lab/monday.session".to_string())));
+        assert_eq!(
+            parse("load session monday.session"),
+            Ok(Command::LoadSession("monday.session".to_string())));
+        assert!(parse("save session").is_err());
+    }
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            parse("source exercises/cesar.crypt"),
+            Ok(Command::Source("exercises/cesar.crypt".to_string(), OnError::Stop)));
+        assert_eq!(
+            parse("source cesar.crypt continue"),
+            Ok(Command::Source("cesar.crypt".to_string(), OnError::Continue)));
+        assert!(parse("source").is_err());
+    }
+
+    #[test]
+    fn test_parse_key_value_text() {
+        for key in [
+            KeyValue::Integer(-3),
+            KeyValue::Pair(5, 8),
+            KeyValue::Matrix(vec![vec![3, 3], vec![2, 5]]),
+            KeyValue::Seeded(0xace1, vec![16, 14, 13, 11]),
+            KeyValue::Registers(vec![(1, vec![7, 6]), (2, vec![9, 5])]),
+            KeyValue::Framed(0x1223456789abcdef, 0x134),
+            KeyValue::String("lemon".to_string()),
+            KeyValue::String("it's \"quoted\"\n\\ é".to_string())] {
+            assert_eq!(parse_key_value_text(&key.syntax()), Ok(key));
+        }
+    }
+
+    #[test]
+    fn test_parse_seeded() {
+        assert_eq!(
+            parse("key 0xACE1 [16,14,13,11]"),
+            Ok(Command::Key(KeyValue::Seeded(0xACE1, vec![16, 14, 13, 11]))));
+        assert_eq!(
+            parse("key 5 [4, 3]"),
+            Ok(Command::Key(KeyValue::Seeded(5, vec![4, 3]))));
+        assert_eq!(
+            parse("key 0x5b [7,6], 0xa7 [8,6,5,4]"),
+            Ok(Command::Key(KeyValue::Registers(vec![(0x5b, vec![7, 6]), (0xa7, vec![8, 6, 5, 4])]))));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("key 300"),
+            Ok(Command::Key(KeyValue::Integer(300))));
+        assert_eq!(
+            parse("key -3"),
+            Ok(Command::Key(KeyValue::Integer(-3))));
+        assert_eq!(
+            parse("key -99999999999999999999"),
+            Err(CryptoError::KeyOutOfRange("-99999999999999999999".to_string())));
+        assert_eq!(
+            parse("encrypt \"hello\" whit 3"),
+            Err(CryptoError::Parse { position: 15, found: "whit".to_string() }));
+        assert_eq!(
+            parse("statu"),
+            Err(CryptoError::Parse { position: 0, found: "statu".to_string() }));
+        assert_eq!(parse("  "), Ok(Command::Empty));
     }
 
 }
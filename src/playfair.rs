@@ -0,0 +1,324 @@
+// Playfair cipher on a keyword-built 5x5 square
+
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::{Key, Cipher};
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::policy::{NonAlphabet, Policy};
+use crate::registry::CipherDescriptor;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "playfair",
+    aliases: &[],
+    key_type: "string",
+    description: "encrypt pairs of letters with a keyword-built 5x5 square",
+    build: || Box::new(Playfair {
+        key: "".to_string(),
+        message: "".to_string(),
+        merge: ('j', 'i'),
+        filler: 'x',
+        alphabet: Alphabet::default(),
+        policy: Policy::default()
+    }),
+};
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Playfair {
+    pub key: String,
+    pub message: String,
+    /// The first letter is replaced by the second one to fit 25 letters.
+    pub merge: (char, char),
+    pub filler: char,
+    pub alphabet: Alphabet,
+    pub policy: Policy
+}
+
+/// The 5x5 square, stored row by row.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Square {
+    letters: Vec<char>
+}
+
+impl Square {
+    fn position(&self, c: char) -> (usize, usize) {
+        let i = self.letters.iter().position(|l| *l == c).expect("letters are folded into the square");
+        (i / 5, i % 5)
+    }
+
+    fn at(&self, row: usize, col: usize) -> char {
+        self.letters[(row % 5) * 5 + col % 5]
+    }
+
+    /// Apply the row, column and rectangle rules to one digraph; `step` is
+    /// 1 to encrypt and 4 to decrypt.
+    fn digraph(&self, a: char, b: char, step: usize) -> (char, char) {
+        let (ra, ca) = self.position(a);
+        let (rb, cb) = self.position(b);
+        if ra == rb {
+            (self.at(ra, ca + step), self.at(rb, cb + step))
+        } else if ca == cb {
+            (self.at(ra + step, ca), self.at(rb + step, cb))
+        } else {
+            (self.at(ra, cb), self.at(rb, ca))
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        self.letters.chunks(5)
+            .map(|row| row.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(" "))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+impl Playfair {
+    fn fold(&self, c: char) -> Option<char> {
+        let c = c.to_lowercase().next()?;
+        match c {
+            'a'..='z' if c == self.merge.0 => Some(self.merge.1),
+            'a'..='z' => Some(c),
+            _ => None
+        }
+    }
+
+    pub fn square(&self) -> Square {
+        let mut letters = vec![];
+        for c in self.key.chars().chain('a'..='z').filter_map(|c| self.fold(c)) {
+            if !letters.contains(&c) {
+                letters.push(c);
+            }
+        }
+        Square { letters }
+    }
+
+    /// Keep the letters of `msg` and split them into digraphs, inserting the
+    /// filler between doubled letters and at the end when needed.
+    fn digraphs(&self, msg: &str) -> Result<Vec<(char, char)>, CryptoError> {
+        let mut letters = vec![];
+        for c in msg.chars() {
+            match (self.fold(c), self.policy.non_alphabet) {
+                (Some(l), _) => letters.push(l),
+                (None, NonAlphabet::Error) if !c.is_whitespace() => return Err(CryptoError::InvalidCharacter(c)),
+                (None, _) => ()
+            }
+        }
+        let mut res = vec![];
+        let mut i = 0;
+        while i < letters.len() {
+            let a = letters[i];
+            match letters.get(i + 1) {
+                Some(b) if *b != a => {
+                    res.push((a, *b));
+                    i += 2;
+                },
+                _ => {
+                    res.push((a, self.filler_for(a)));
+                    i += 1;
+                }
+            }
+        }
+        Ok(res)
+    }
+
+    /// The cipher with one option changed, not yet checked.
+    fn with_option(&self, option: &str, value: &str) -> Result<Playfair, CryptoError> {
+        match option {
+            "merge" => match value.chars().collect::<Vec<char>>().as_slice() {
+                [from, to] if from.is_ascii_lowercase() && to.is_ascii_lowercase() && from != to =>
+                    Ok(Playfair { merge: (*from, *to), ..self.clone() }),
+                _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+            },
+            "filler" => Ok(Playfair { filler: letter(option, value)?, ..self.clone() }),
+            _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+        }
+    }
+
+    /// The filler must stay in the square, so it can't be the merged letter.
+    fn checked(self, option: &str, value: &str) -> Result<Playfair, CryptoError> {
+        if self.filler == self.merge.0 {
+            return Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() });
+        }
+        Ok(self)
+    }
+
+    /// The filler, or `q`, `z` or `x` when the letter is the filler itself:
+    /// one of them is neither `c` nor merged away.
+    fn filler_for(&self, c: char) -> char {
+        [self.filler, 'q', 'z', 'x'].into_iter()
+            .find(|f| *f != c && *f != self.merge.0)
+            .expect("two letters at most are excluded")
+    }
+
+    fn compute(&self, msg: &str, step: usize) -> Result<String, CryptoError> {
+        let square = self.square();
+        Ok(self.digraphs(msg)?.into_iter()
+            .map(|(a, b)| square.digraph(a, b, step))
+            .flat_map(|(a, b)| [a, b])
+            .collect())
+    }
+}
+
+fn letter(option: &str, value: &str) -> Result<char, CryptoError> {
+    match value.chars().collect::<Vec<char>>().as_slice() {
+        [c] if c.is_ascii_lowercase() => Ok(*c),
+        _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+    }
+}
+
+impl Cipher for Playfair {
+    fn name(&self) -> &'static str {
+        "playfair"
+    }
+
+    fn title(&self) -> &'static str {
+        "Playfair"
+    }
+
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        self.compute(msg, 1)
+    }
+
+    fn decrypt(&self, ct: &str) -> Result<String, CryptoError> {
+        self.compute(ct, 4)
+    }
+
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let value = String::from_key(self.name(), key)?;
+        Ok(Box::new(Playfair {
+            key: value,
+            ..self.clone()
+        }))
+    }
+
+    fn describe(&self) -> String {
+        format!("{} (merge {} into {}, filler {})", self.key.describe(), self.merge.0, self.merge.1, self.filler)
+    }
+
+    fn show_key(&self) -> String {
+        self.square().describe()
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Playfair {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        Some(&self.alphabet)
+    }
+
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        if *alphabet != Alphabet::lowercase() {
+            return Err(CryptoError::UnsupportedAlphabet {
+                algo: self.name().to_string(),
+                alphabet: alphabet.name().to_string()
+            });
+        }
+        Ok(self.clone_box())
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        Box::new(Playfair {
+            policy,
+            ..self.clone()
+        })
+    }
+
+    fn set_option(&self, option: &str, value: &str) -> Result<Box<dyn Cipher>, CryptoError> {
+        Ok(Box::new(self.with_option(option, value)?.checked(option, value)?))
+    }
+
+    fn set_options(&self, options: &[(String, String)]) -> Result<Box<dyn Cipher>, CryptoError> {
+        let playfair = options.iter().try_fold(self.clone(), |p, (o, v)| p.with_option(o, v))?;
+        let all = options.iter().map(|(o, v)| format!("{} {}", o, v)).collect::<Vec<String>>().join(", ");
+        Ok(Box::new(playfair.checked("options", &all)?))
+    }
+
+    fn options(&self) -> Vec<(&'static str, String)> {
+        vec![("merge", format!("{}{}", self.merge.0, self.merge.1)), ("filler", self.filler.to_string())]
+    }
+
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playfair(key: &str, message: &str) -> Playfair {
+        Playfair {
+            key: key.to_string(),
+            message: message.to_string(),
+            merge: ('j', 'i'),
+            filler: 'x',
+            alphabet: Alphabet::default(),
+            policy: Policy::default()
+        }
+    }
+
+    #[test]
+    fn test_square() {
+        assert_eq!(
+            playfair("playfair example", "").show_key(),
+            "p l a y f\ni r e x m\nb c d g h\nk n o q s\nt u v w z".to_string());
+    }
+
+    #[test]
+    fn test_playfair_encrypt() {
+        assert_eq!(
+            playfair("playfair example", "hide the gold in the tree stump").encrypt2(),
+            Ok("bmodzbxdnabekudmuixmmouvif".to_string()));
+    }
+
+    #[test]
+    fn test_playfair_decrypt() {
+        assert_eq!(
+            playfair("playfair example", "bmodzbxdnabekudmuixmmouvif").decrypt2(),
+            Ok("hidethegoldinthetrexestump".to_string()));
+    }
+
+    #[test]
+    fn test_digraphs() {
+        assert_eq!(
+            playfair("", "").digraphs("balloon x"),
+            Ok(vec![('b', 'a'), ('l', 'x'), ('l', 'o'), ('o', 'n'), ('x', 'q')]));
+    }
+
+    #[test]
+    fn test_filler_merged_away() {
+        let p = Playfair { merge: ('q', 'k'), ..playfair("", "") };
+        assert_eq!(p.digraphs("xx"), Ok(vec![('x', 'z'), ('x', 'z')]));
+        let p = Playfair { merge: ('z', 's'), filler: 'q', ..playfair("", "q") };
+        assert_eq!(p.digraphs("q"), Ok(vec![('q', 'x')]));
+        let ct = p.encrypt2().unwrap();
+        assert_eq!(p.decrypt(&ct), Ok("qx".to_string()));
+    }
+
+    #[test]
+    fn test_playfair_options() {
+        let p = playfair("keyword", "");
+        let merged = p.set_option("merge", "qk").unwrap();
+        assert!(!merged.show_key().contains('q'));
+        assert!(merged.show_key().contains('j'));
+        assert!(p.set_option("filler", "xy").is_err());
+        assert!(p.set_option("filler", "j").is_err());
+        assert!(p.set_option("merge", "xi").is_err());
+        let options = [("merge", "xk"), ("filler", "j")].map(|(o, v)| (o.to_string(), v.to_string()));
+        assert_eq!(p.set_options(&options).unwrap().options(), vec![("merge", "xk".to_string()), ("filler", "j".to_string())]);
+        assert!(p.set_options(&options[..1]).is_err());
+        assert!(p.set_alphabet(&Alphabet::mixed()).is_err());
+    }
+
+}
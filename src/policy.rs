@@ -0,0 +1,163 @@
+// What the classical ciphers do with the characters outside their alphabet
+
+use crate::alphabet::Alphabet;
+use crate::error::CryptoError;
+use crate::language::Language;
+use crate::tools::Zmod;
+
+/// Handling of the characters that are not in the alphabet.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum NonAlphabet {
+    PassThrough,
+    Strip,
+    Error,
+}
+
+/// The session settings shared by every classical cipher.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Policy {
+    pub non_alphabet: NonAlphabet,
+    pub preserve_case: bool,
+    pub key_on_letters_only: bool,
+    pub language: Language,
+}
+
+impl Default for Policy {
+    fn default() -> Policy {
+        Policy {
+            non_alphabet: NonAlphabet::PassThrough,
+            preserve_case: true,
+            key_on_letters_only: true,
+            language: Language::English,
+        }
+    }
+}
+
+fn switch(option: &str, value: &str) -> Result<bool, CryptoError> {
+    match value {
+        "on" | "true" | "yes" => Ok(true),
+        "off" | "false" | "no" => Ok(false),
+        _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+    }
+}
+
+impl Policy {
+    pub fn options() -> Vec<&'static str> {
+        vec!["nonalpha [pass|strip|error]", "case [on|off] (off: output in the case of the alphabet)", "keyletters [on|off]", "language [english|french]"]
+    }
+
+    pub fn has_option(option: &str) -> bool {
+        matches!(option, "nonalpha" | "case" | "keyletters" | "language")
+    }
+
+    /// Change one option, as written with `set <option> <value>`.
+    pub fn set(&self, option: &str, value: &str) -> Result<Policy, CryptoError> {
+        match option {
+            "nonalpha" => {
+                let non_alphabet = match value {
+                    "pass" => NonAlphabet::PassThrough,
+                    "strip" => NonAlphabet::Strip,
+                    "error" => NonAlphabet::Error,
+                    _ => return Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+                };
+                Ok(Policy { non_alphabet, ..*self })
+            },
+            "case" => Ok(Policy { preserve_case: switch(option, value)?, ..*self }),
+            "keyletters" => Ok(Policy { key_on_letters_only: switch(option, value)?, ..*self }),
+            "language" => Ok(Policy { language: Language::named(value)?, ..*self }),
+            _ => Err(CryptoError::InvalidOption { option: option.to_string(), value: value.to_string() })
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let non_alphabet = match self.non_alphabet {
+            NonAlphabet::PassThrough => "pass",
+            NonAlphabet::Strip => "strip",
+            NonAlphabet::Error => "error",
+        };
+        let on_off = |b: bool| if b { "on" } else { "off" };
+        format!("nonalpha {}, case {}, keyletters {}, language {}",
+            non_alphabet, on_off(self.preserve_case), on_off(self.key_on_letters_only), self.language.name())
+    }
+
+    /// Map every letter of `msg` through `f`, which also gets the position of
+    /// the letter in the key stream, and treat the other characters according
+    /// to the policy. A letter of the other case is folded into the alphabet;
+    /// with `case off` it comes out in the case of the alphabet.
+    pub fn apply<F>(&self, msg: &str, alphabet: &Alphabet, mut f: F) -> Result<String, CryptoError>
+        where F: FnMut(usize, Zmod) -> Zmod {
+        let mut res = String::new();
+        let mut letters = 0;
+        for (i, c) in msg.chars().enumerate() {
+            let position = if self.key_on_letters_only { letters } else { i };
+            if let Ok(n) = alphabet.letter_to_number(c) {
+                res.push(alphabet.number_to_letter(f(position, n)));
+                letters += 1;
+                continue;
+            }
+            let folded = c.to_lowercase().next().filter(|l| *l != c && alphabet.contains(*l))
+                .or_else(|| c.to_uppercase().next().filter(|u| *u != c && alphabet.contains(*u)));
+            match (folded, self.non_alphabet) {
+                (Some(l), _) => {
+                    let out = alphabet.number_to_letter(f(position, alphabet.letter_to_number(l)?));
+                    match (self.preserve_case, c.is_uppercase()) {
+                        (false, _) => res.push(out),
+                        (true, true) => res.extend(out.to_uppercase()),
+                        (true, false) => res.extend(out.to_lowercase()),
+                    }
+                    letters += 1;
+                },
+                (_, NonAlphabet::PassThrough) => res.push(c),
+                (_, NonAlphabet::Strip) => (),
+                (_, NonAlphabet::Error) => return Err(CryptoError::InvalidCharacter(c)),
+            }
+        }
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shift(policy: &Policy, msg: &str) -> Result<String, CryptoError> {
+        policy.apply(msg, &Alphabet::lowercase(), |_, n| n + Zmod::new(1, 26))
+    }
+
+    #[test]
+    fn test_apply_pass_through() {
+        assert_eq!(
+            shift(&Policy::default(), "Hello, World!"),
+            Ok("Ifmmp, Xpsme!".to_string()));
+    }
+
+    #[test]
+    fn test_apply_strip_and_error() {
+        let strip = Policy::default().set("nonalpha", "strip").unwrap();
+        assert_eq!(shift(&strip, "Hello, World!"), Ok("IfmmpXpsme".to_string()));
+        let error = Policy::default().set("nonalpha", "error").unwrap();
+        assert_eq!(shift(&error, "Hello, World!"), Err(CryptoError::InvalidCharacter(',')));
+        let no_case = error.set("case", "off").unwrap();
+        assert_eq!(shift(&no_case, "Hello"), Ok("ifmmp".to_string()));
+        assert_eq!(shift(&no_case, "Hi!"), Err(CryptoError::InvalidCharacter('!')));
+    }
+
+    #[test]
+    fn test_apply_positions() {
+        let mut positions = vec![];
+        Policy::default().apply("a b", &Alphabet::lowercase(), |i, n| { positions.push(i); n }).unwrap();
+        assert_eq!(positions, vec![0, 1]);
+        let mut positions = vec![];
+        let all = Policy::default().set("keyletters", "off").unwrap();
+        all.apply("a b", &Alphabet::lowercase(), |i, n| { positions.push(i); n }).unwrap();
+        assert_eq!(positions, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_set_invalid() {
+        assert_eq!(
+            Policy::default().set("case", "maybe"),
+            Err(CryptoError::InvalidOption { option: "case".to_string(), value: "maybe".to_string() }));
+    }
+
+}
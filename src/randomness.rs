@@ -0,0 +1,354 @@
+// Statistical tests of NIST SP 800-22 for keystreams
+
+use std::f64::consts::{LN_2, PI};
+use crate::cryptanalysis::berlekamp_massey;
+use crate::error::CryptoError;
+use crate::stream;
+
+/// Significance level: a test passes when its p-value is at least this.
+pub const ALPHA: f64 = 0.01;
+
+/// Natural logarithm of the gamma function (Lanczos approximation).
+fn ln_gamma(x: f64) -> f64 {
+    const G: [f64; 9] = [
+        0.999_999_999_999_81,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_403,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+    let x = x - 1.0;
+    let a = (1..9).fold(G[0], |acc, i| acc + G[i] / (x + i as f64));
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + a.ln()
+}
+
+/// Regularized upper incomplete gamma function Q(a, x).
+pub fn igamc(a: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 1.0;
+    }
+    let front = (-x + a * x.ln() - ln_gamma(a)).exp();
+    if x < a + 1.0 {
+        let mut term = 1.0 / a;
+        let mut sum = term;
+        for n in 1..1000 {
+            term *= x / (a + n as f64);
+            sum += term;
+            if term.abs() < sum.abs() * 1e-15 {
+                break;
+            }
+        }
+        1.0 - sum * front
+    } else {
+        let tiny = 1e-300;
+        let mut b = x + 1.0 - a;
+        let mut c = 1.0 / tiny;
+        let mut d = 1.0 / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -(i as f64) * (i as f64 - a);
+            b += 2.0;
+            d = an * d + b;
+            if d.abs() < tiny {
+                d = tiny;
+            }
+            c = b + an / c;
+            if c.abs() < tiny {
+                c = tiny;
+            }
+            d = 1.0 / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.0).abs() < 1e-15 {
+                break;
+            }
+        }
+        front * h
+    }
+}
+
+/// Complementary error function, as Q(1/2, x²).
+pub fn erfc(x: f64) -> f64 {
+    if x < 0.0 {
+        2.0 - erfc(-x)
+    } else {
+        igamc(0.5, x * x)
+    }
+}
+
+/// Cumulative distribution function of the standard normal law.
+fn normal(z: f64) -> f64 {
+    erfc(-z / 2f64.sqrt()) / 2.0
+}
+
+fn too_short(bits: &[u8], needed: usize) -> Result<(), CryptoError> {
+    if bits.len() < needed {
+        Err(CryptoError::NotEnoughBits { needed, found: bits.len() })
+    } else {
+        Ok(())
+    }
+}
+
+/// The longest patterns counted, their table having `2^m` entries.
+const MAX_PATTERN: usize = 20;
+
+/// Checks the length `m` of the patterns, from `least` bits to [`MAX_PATTERN`].
+fn pattern_length(m: usize, least: usize) -> Result<(), CryptoError> {
+    if m < least || m > MAX_PATTERN {
+        Err(CryptoError::InvalidOption { option: "m".to_string(), value: m.to_string() })
+    } else {
+        Ok(())
+    }
+}
+
+/// Partial sums of the bits as ±1.
+fn sums(bits: &[u8]) -> impl Iterator<Item = i64> + '_ {
+    bits.iter().scan(0, |s, b| {
+        *s += if *b == 1 { 1 } else { -1 };
+        Some(*s)
+    })
+}
+
+/// Frequency (monobit) test: as many ones as zeros.
+pub fn monobit(bits: &[u8]) -> Result<f64, CryptoError> {
+    too_short(bits, 1)?;
+    let s = sums(bits).last().unwrap_or(0);
+    Ok(erfc(s.abs() as f64 / (bits.len() as f64).sqrt() / 2f64.sqrt()))
+}
+
+/// Frequency test within blocks of `m` bits.
+pub fn block_frequency(bits: &[u8], m: usize) -> Result<f64, CryptoError> {
+    too_short(bits, m)?;
+    let blocks = bits.len() / m;
+    let chi2 = 4.0 * m as f64 * bits.chunks_exact(m)
+        .map(|block| (block.iter().filter(|b| **b == 1).count() as f64 / m as f64 - 0.5).powi(2))
+        .sum::<f64>();
+    Ok(igamc(blocks as f64 / 2.0, chi2 / 2.0))
+}
+
+/// Runs test: the number of runs of identical bits; 0 when the sequence
+/// fails the frequency prerequisite.
+pub fn runs(bits: &[u8]) -> Result<f64, CryptoError> {
+    too_short(bits, 2)?;
+    let n = bits.len() as f64;
+    let pi = bits.iter().filter(|b| **b == 1).count() as f64 / n;
+    if (pi - 0.5).abs() >= 2.0 / n.sqrt() {
+        return Ok(0.0);
+    }
+    let v = 1 + bits.windows(2).filter(|w| w[0] != w[1]).count();
+    Ok(erfc((v as f64 - 2.0 * n * pi * (1.0 - pi)).abs() / (2.0 * (2.0 * n).sqrt() * pi * (1.0 - pi))))
+}
+
+/// Counts of the overlapping patterns of `m` bits, the sequence wrapping around.
+fn patterns(bits: &[u8], m: usize) -> Vec<usize> {
+    let mut counts = vec![0; 1 << m];
+    if m == 0 {
+        return counts;
+    }
+    for i in 0..bits.len() {
+        let pattern = (0..m).fold(0, |acc, j| (acc << 1) | bits[(i + j) % bits.len()] as usize);
+        counts[pattern] += 1;
+    }
+    counts
+}
+
+fn psi2(bits: &[u8], m: usize) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+    let n = bits.len() as f64;
+    (1 << m) as f64 / n * patterns(bits, m).iter().map(|c| (*c as f64).powi(2)).sum::<f64>() - n
+}
+
+/// Serial test: every pattern of `m` bits is as frequent; two p-values.
+pub fn serial(bits: &[u8], m: usize) -> Result<(f64, f64), CryptoError> {
+    pattern_length(m, 2)?;
+    too_short(bits, m.max(3))?;
+    let (p0, p1, p2) = (psi2(bits, m), psi2(bits, m - 1), psi2(bits, m.saturating_sub(2)));
+    Ok((
+        igamc((1 << (m - 1)) as f64 / 2.0, (p0 - p1) / 2.0),
+        igamc((1 << (m - 1)) as f64 / 4.0, (p0 - 2.0 * p1 + p2) / 2.0)
+    ))
+}
+
+fn phi(bits: &[u8], m: usize) -> f64 {
+    let n = bits.len() as f64;
+    patterns(bits, m).iter()
+        .filter(|c| **c > 0)
+        .map(|c| *c as f64 / n * (*c as f64 / n).ln())
+        .sum()
+}
+
+/// Approximate entropy test: patterns of `m` and `m + 1` bits.
+pub fn approximate_entropy(bits: &[u8], m: usize) -> Result<f64, CryptoError> {
+    pattern_length(m + 1, 1)?;
+    too_short(bits, m + 1)?;
+    let n = bits.len() as f64;
+    let apen = phi(bits, m) - phi(bits, m + 1);
+    Ok(igamc((1 << m) as f64 / 2.0, n * (LN_2 - apen)))
+}
+
+/// Cumulative sums test, from the first bit or from the last one.
+pub fn cumulative_sums(bits: &[u8], forward: bool) -> Result<f64, CryptoError> {
+    too_short(bits, 1)?;
+    let z = if forward {
+        sums(bits).map(i64::abs).max()
+    } else {
+        sums(&bits.iter().rev().copied().collect::<Vec<u8>>()).map(i64::abs).max()
+    }.unwrap_or(0) as f64;
+    let n = bits.len() as f64;
+    let sqrt_n = n.sqrt();
+    // The bounds are truncated towards zero as in the reference implementation.
+    let first = ((-n / z + 1.0) / 4.0) as i64..=((n / z - 1.0) / 4.0) as i64;
+    let second = ((-n / z - 3.0) / 4.0) as i64..=((n / z - 1.0) / 4.0) as i64;
+    let sum1 = first.map(|k| k as f64)
+        .map(|k| normal((4.0 * k + 1.0) * z / sqrt_n) - normal((4.0 * k - 1.0) * z / sqrt_n))
+        .sum::<f64>();
+    let sum2 = second.map(|k| k as f64)
+        .map(|k| normal((4.0 * k + 3.0) * z / sqrt_n) - normal((4.0 * k + 1.0) * z / sqrt_n))
+        .sum::<f64>();
+    Ok((1.0 - sum1 + sum2).min(1.0))
+}
+
+/// Linear complexity test over blocks of `m` bits, with at least 200 blocks.
+pub fn linear_complexity(bits: &[u8], m: usize) -> Result<f64, CryptoError> {
+    const PI: [f64; 7] = [0.010417, 0.03125, 0.125, 0.5, 0.25, 0.0625, 0.020833];
+    too_short(bits, 200 * m)?;
+    let mf = m as f64;
+    let sign = if m.is_multiple_of(2) { 1.0 } else { -1.0 };
+    let mu = mf / 2.0 + (9.0 - sign) / 36.0 - (mf / 3.0 + 2.0 / 9.0) / 2f64.powi(m as i32);
+    let mut v = [0.0; 7];
+    for block in bits.chunks_exact(m) {
+        let t = sign * (berlekamp_massey(block).complexity as f64 - mu) + 2.0 / 9.0;
+        let bin = match t {
+            t if t <= -2.5 => 0,
+            t if t <= -1.5 => 1,
+            t if t <= -0.5 => 2,
+            t if t <= 0.5 => 3,
+            t if t <= 1.5 => 4,
+            t if t <= 2.5 => 5,
+            _ => 6
+        };
+        v[bin] += 1.0;
+    }
+    let blocks = (bits.len() / m) as f64;
+    let chi2 = v.iter().zip(PI)
+        .map(|(v, p)| (v - blocks * p).powi(2) / (blocks * p))
+        .sum::<f64>();
+    Ok(igamc(3.0, chi2 / 2.0))
+}
+
+/// Run every test with parameters chosen from the length of `bits`; gives the
+/// name of each test with its p-values.
+pub fn suite(bits: &[u8]) -> Vec<(String, Result<Vec<f64>, CryptoError>)> {
+    let n = bits.len();
+    let log2 = (usize::BITS - n.leading_zeros()) as usize;
+    let block = 20.max(n / 100 + 1);
+    let serial_m = log2.saturating_sub(3).clamp(2, 16);
+    let apen_m = log2.saturating_sub(6).clamp(2, 10);
+    vec![
+        ("monobit".to_string(), monobit(bits).map(|p| vec![p])),
+        (format!("block frequency (M={})", block), block_frequency(bits, block).map(|p| vec![p])),
+        ("runs".to_string(), runs(bits).map(|p| vec![p])),
+        (format!("serial (m={})", serial_m), serial(bits, serial_m).map(|(p1, p2)| vec![p1, p2])),
+        (format!("approximate entropy (m={})", apen_m), approximate_entropy(bits, apen_m).map(|p| vec![p])),
+        ("cumulative sums forward".to_string(), cumulative_sums(bits, true).map(|p| vec![p])),
+        ("cumulative sums backward".to_string(), cumulative_sums(bits, false).map(|p| vec![p])),
+        ("linear complexity (M=500)".to_string(), linear_complexity(bits, 500).map(|p| vec![p])),
+    ]
+}
+
+/// The suite as a table of p-values.
+pub fn report(bits: &[u8]) -> String {
+    let mut lines = vec![
+        format!("{} bits", bits.len()),
+        format!("{:<28} {:<9} result", "test", "p-value"),
+    ];
+    for (name, res) in suite(bits) {
+        match res {
+            Ok(p_values) => for (i, p) in p_values.iter().enumerate() {
+                lines.push(format!("{:<28} {:<9.6} {}",
+                    if i == 0 { name.as_str() } else { "" },
+                    p,
+                    if *p >= ALPHA { "pass" } else { "FAIL" }));
+            },
+            Err(e) => lines.push(format!("{:<28} skipped: {}", name, e))
+        }
+    }
+    lines.join("\n")
+}
+
+/// Bits of a file: a text of `0` and `1`, or else every byte.
+pub fn read_bits(path: &str) -> Result<Vec<u8>, CryptoError> {
+    let data = std::fs::read(path)
+        .map_err(|e| CryptoError::Io { path: path.to_string(), reason: e.to_string() })?;
+    if data.iter().all(|b| b"01 \t\r\n".contains(b)) {
+        stream::bits(&String::from_utf8_lossy(&data))
+    } else {
+        Ok(stream::unpack(&data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::a5::A5;
+    use crate::lfsr::{Lfsr, Mode};
+
+    fn bits(s: &str) -> Vec<u8> {
+        stream::bits(s).unwrap()
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-6
+    }
+
+    #[test]
+    fn test_special_functions() {
+        assert!(close(erfc(0.0), 1.0));
+        assert!(close(erfc(1.0), 0.157299207));
+        assert!(close(erfc(-1.0), 1.842700793));
+        assert!(close(igamc(1.0, 2.0), (-2.0f64).exp()));
+        assert!(close(igamc(3.0, 10.0), 0.002769396));
+        assert!(close(ln_gamma(5.0), 24f64.ln()));
+    }
+
+    #[test]
+    fn test_reference_examples() {
+        assert!(close(monobit(&bits("1011010101")).unwrap(), 0.527089));
+        assert!(close(block_frequency(&bits("0110011010"), 3).unwrap(), 0.801252));
+        assert!(close(runs(&bits("1001101011")).unwrap(), 0.147232));
+        let (p1, p2) = serial(&bits("0011011101"), 3).unwrap();
+        assert!(close(p1, 0.808792) && close(p2, 0.670320), "{} {}", p1, p2);
+        assert!(close(approximate_entropy(&bits("0100110101"), 3).unwrap(), 0.261961));
+        assert!(matches!(serial(&bits("0011011101"), 0), Err(CryptoError::InvalidOption { .. })));
+        assert!(matches!(serial(&bits("0011011101"), 1), Err(CryptoError::InvalidOption { .. })));
+        assert!(matches!(approximate_entropy(&bits("0100110101"), 64), Err(CryptoError::InvalidOption { .. })));
+        assert!(close(cumulative_sums(&bits("1011010111"), true).unwrap(), 0.411658));
+    }
+
+    #[test]
+    fn test_generators() {
+        let a5 = A5::new([0x12, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF], 0x134).take(100_000).collect::<Vec<u8>>();
+        assert!(suite(&a5).iter().all(|(_, r)| r.as_ref().unwrap().iter().all(|p| *p >= ALPHA)));
+        let lfsr = Lfsr::from_taps(&[16, 14, 13, 11], 0xACE1, Mode::Fibonacci).unwrap().take(100_000).collect::<Vec<u8>>();
+        assert!(linear_complexity(&lfsr, 500).unwrap() < ALPHA);
+        assert_eq!(runs(&[1; 100]), Ok(0.0));
+    }
+
+    #[test]
+    fn test_report() {
+        let report = report(&bits("1011010101"));
+        assert!(report.contains("monobit                      0.527089  pass"), "{}", report);
+        assert!(report.contains("skipped: at least 100000 bits are needed, found 10"), "{}", report);
+    }
+
+}
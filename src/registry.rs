@@ -0,0 +1,110 @@
+// Central list of the available ciphers
+
+use crate::affine;
+use crate::base_cryptography::Cipher;
+use crate::cesar;
+use crate::error::CryptoError;
+use crate::a5;
+use crate::combiner;
+use crate::hill;
+use crate::lfsr;
+use crate::playfair;
+use crate::vigenere;
+
+/// Static description of a cipher and how to build it.
+pub struct CipherDescriptor {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    pub key_type: &'static str,
+    pub description: &'static str,
+    pub build: fn() -> Box<dyn Cipher>,
+}
+
+impl CipherDescriptor {
+    pub fn matches(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+
+    pub fn describe(&self) -> String {
+        format!("{} [{}]: {}", self.name, self.key_type, self.description)
+    }
+}
+
+/// Every cipher selectable with `select`.
+pub const CIPHERS: &[CipherDescriptor] = &[
+    cesar::DESCRIPTOR,
+    affine::DESCRIPTOR,
+    vigenere::DESCRIPTOR,
+    playfair::DESCRIPTOR,
+    hill::DESCRIPTOR,
+    lfsr::DESCRIPTOR,
+    combiner::GEFFE,
+    combiner::SHRINKING,
+    combiner::SELF_SHRINKING,
+    combiner::ALTERNATING,
+    a5::DESCRIPTOR,
+];
+
+pub fn names() -> Vec<&'static str> {
+    CIPHERS.iter().map(|d| d.name).collect()
+}
+
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current.push((previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+pub fn suggestions(name: &str) -> Vec<&'static str> {
+    let mut res = CIPHERS.iter()
+        .filter_map(|d| std::iter::once(d.name).chain(d.aliases.iter().copied())
+            .map(|candidate| distance(name, candidate))
+            .min()
+            .map(|dist| (dist, d.name)))
+        .filter(|(dist, _)| *dist <= 2)
+        .collect::<Vec<(usize, &'static str)>>();
+    res.sort();
+    res.into_iter().map(|(_, n)| n).collect()
+}
+
+/// Look up a cipher by name or alias, with suggestions when it is unknown.
+pub fn find(name: &str) -> Result<&'static CipherDescriptor, CryptoError> {
+    CIPHERS.iter().find(|d| d.matches(name)).ok_or_else(|| CryptoError::UnknownAlgorithm {
+        name: name.to_string(),
+        suggestions: suggestions(name).into_iter().map(str::to_string).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        assert_eq!(find("cesar").unwrap().name, "cesar");
+        assert_eq!(find("caesar").unwrap().name, "cesar");
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("cesar", "cesar"), 0);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(suggestions("vigener"), vec!["vigenere"]);
+        assert_eq!(
+            find("cesr").err().unwrap().to_string(),
+            "unknown algorithm \"cesr\", did you mean cesar or lfsr?");
+    }
+
+}
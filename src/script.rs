@@ -0,0 +1,175 @@
+// Files of REPL commands, run one line at a time
+
+use std::io::Write;
+use crate::base_cryptography::Algo;
+use crate::error::CryptoError;
+use crate::interpreter::eval;
+use crate::parser::commands::OnError;
+use crate::parser::open_heredoc;
+use crate::session::Session;
+
+/// Run the commands of `script` from `context`, one per line, writing their
+/// output to `out`; blank lines and lines starting with `#` are skipped, and
+/// a heredoc goes on to the line closing it.
+/// With `echo` each command follows the prompt and errors are written too, as
+/// in the REPL; without, errors go to `err` with their line. The script ends
+/// at `exit`, or at the first error with [`OnError::Stop`]. Returns the
+/// session and the errors, with their line.
+pub fn execute<W: Write, E: Write>(script: &str, context: &Session, echo: bool, on_error: OnError, out: &mut W, err: &mut E) -> (Session, Vec<CryptoError>) {
+    let mut session = context.clone();
+    let mut errors = vec![];
+    let mut lines = script.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let mut command = line.trim().to_string();
+        if command.is_empty() || command.starts_with('#') {
+            continue;
+        }
+        while open_heredoc(&command).is_some() {
+            match lines.next() {
+                Some((_, line)) => command = format!("{}\n{}", command, line),
+                None => break
+            }
+        }
+        let (next, res) = eval(&command, &session);
+        let written = match (&res, echo) {
+            (_, true) if matches!(next.algo, Algo::Exit) => writeln!(out, "{}{}", session.algo.get_prompt(), command),
+            (Ok(output), true) => writeln!(out, "{}{}\n{}", session.algo.get_prompt(), command, output),
+            (Err(e), true) => writeln!(out, "{}{}\nerror: {}", session.algo.get_prompt(), command, e),
+            (Ok(output), false) if !output.is_empty() => writeln!(out, "{}", output),
+            (Err(e), false) => writeln!(err, "error: line {}: {}", i + 1, e),
+            _ => Ok(())
+        };
+        if let Err(e) = written {
+            errors.push(CryptoError::Io { path: "output".to_string(), reason: e.to_string() });
+            return (session, errors);
+        }
+        if let Algo::Exit = next.algo {
+            break;
+        }
+        session = next;
+        if let Err(e) = res {
+            errors.push(CryptoError::Script { line: i + 1, error: Box::new(e) });
+            if on_error == OnError::Stop {
+                break;
+            }
+        }
+    }
+    (session, errors)
+}
+
+/// How many `source` commands may run one inside the other.
+const MAX_DEPTH: usize = 16;
+
+/// The absolute path of a file, to tell whether it is already being sourced.
+pub fn canonical(path: &str) -> String {
+    std::fs::canonicalize(path).map_or(path.to_string(), |p| p.to_string_lossy().to_string())
+}
+
+/// Run the file `path` as the `source` command does, returning what it wrote,
+/// or the first error with what it wrote when a command failed. A file can't
+/// source itself, directly or not.
+pub fn source(path: &str, context: &Session, on_error: OnError) -> (Session, Result<String, CryptoError>) {
+    let file = canonical(path);
+    if context.sources.contains(&file) {
+        return (context.clone(), Err(CryptoError::RecursiveSource(path.to_string())));
+    }
+    if context.sources.len() >= MAX_DEPTH {
+        return (context.clone(), Err(CryptoError::SourceTooDeep));
+    }
+    match std::fs::read_to_string(path) {
+        Ok(script) => {
+            let mut out = vec![];
+            let mut sources = context.sources.clone();
+            sources.push(file);
+            let inner = Session { sources, ..context.clone() };
+            let (session, errors) = execute(&script, &inner, true, on_error, &mut out, &mut std::io::sink());
+            let session = Session { sources: context.sources.clone(), ..session };
+            let transcript = String::from_utf8_lossy(&out).trim_end().to_string();
+            match errors.into_iter().next() {
+                Some(e) => (session, Err(CryptoError::Sourced { path: path.to_string(), error: Box::new(e), transcript })),
+                None => (session, Ok(transcript))
+            }
+        },
+        Err(e) => (context.clone(), Err(CryptoError::Io { path: path.to_string(), reason: e.to_string() }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "# Cesar exercise\nselect cesar\n\nkey 3\nkey \"abc\"\nencrypt \"abc\" with 3\nexit\nstatus\n";
+
+    #[test]
+    fn test_execute_echo() {
+        let mut out = vec![];
+        let (session, errors) = execute(SCRIPT, &Session::new(), true, OnError::Continue, &mut out, &mut std::io::sink());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            ":[?]:> select cesar\nCesar algorithm selected.\n\
+             :[Cesar]:> key 3\nKey value set\n\
+             :[Cesar]:> key \"abc\"\nerror: the algorithm cesar doesn't work with a key \"abc\" [string]. hint: try to change the key type\n\
+             :[Cesar]:> encrypt \"abc\" with 3\ndef\n\
+             :[Cesar]:> exit\n");
+        assert!(matches!(errors.as_slice(), [CryptoError::Script { line: 5, .. }]));
+        assert_eq!(session.last, Some("def".to_string()));
+    }
+
+    #[test]
+    fn test_execute_stop() {
+        let (mut out, mut err) = (vec![], vec![]);
+        let (session, errors) = execute(SCRIPT, &Session::new(), false, OnError::Stop, &mut out, &mut err);
+        assert_eq!(String::from_utf8(out).unwrap(), "Cesar algorithm selected.\nKey value set\n");
+        assert!(String::from_utf8(err).unwrap().starts_with("error: line 5: the algorithm cesar"));
+        assert!(matches!(errors.as_slice(), [CryptoError::Script { line: 5, .. }]));
+        assert_eq!(session.last, None);
+    }
+
+    #[test]
+    fn test_execute_continue_quiet() {
+        let (mut out, mut err) = (vec![], vec![]);
+        let script = "select cesar\nkey \"a\"\nencrypt \"abc\" with 1\nkey \"b\"\n";
+        let (_, errors) = execute(script, &Session::new(), false, OnError::Continue, &mut out, &mut err);
+        assert_eq!(String::from_utf8(out).unwrap(), "Cesar algorithm selected.\nbcd\n");
+        let err = String::from_utf8(err).unwrap();
+        assert_eq!(err.lines().map(|l| &l[..13]).collect::<Vec<&str>>(), vec!["error: line 2", "error: line 4"]);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_execute_heredoc() {
+        let mut out = vec![];
+        let script = "select cesar\nencrypt <<END\nabc\n# not a comment\nEND with 1\nstatus";
+        let (session, errors) = execute(script, &Session::new(), false, OnError::Stop, &mut out, &mut std::io::sink());
+        assert_eq!(errors, vec![]);
+        assert_eq!(session.last, Some("bcd\n# opu b dpnnfou".to_string()));
+    }
+
+    #[test]
+    fn test_sourced_failure() {
+        let path = std::env::temp_dir().join(format!("cryptography-{}-inner.crypt", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "select cesar\nkey \"abc\"\nkey 3\n").unwrap();
+        let mut err = vec![];
+        let (session, errors) = execute(&format!("source {} continue\nstatus", path), &Session::new(), false, OnError::Stop, &mut vec![], &mut err);
+        std::fs::remove_file(path).unwrap();
+        assert!(matches!(errors.as_slice(), [CryptoError::Script { line: 1, .. }]), "{:?}", errors);
+        assert!(String::from_utf8(err).unwrap().starts_with(&format!("error: line 1: {}: line 2: the algorithm cesar", path)));
+        assert_eq!(session.get_key(), "3");
+    }
+
+    #[test]
+    fn test_source_itself() {
+        let path = std::env::temp_dir().join(format!("cryptography-{}-loop.crypt", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, format!("select cesar\nsource {}\n", path)).unwrap();
+        let (session, res) = source(path, &Session::new(), OnError::Continue);
+        std::fs::remove_file(path).unwrap();
+        let error = res.unwrap_err().to_string();
+        assert!(error.starts_with(&format!("{}: line 2: recursive source of {}", path, path)), "{}", error);
+        assert!(error.ends_with(&format!("error: recursive source of {}", path)), "{}", error);
+        assert_eq!(session.algo.get_algo(), "cesar");
+        assert!(session.sources.is_empty());
+    }
+
+}
@@ -0,0 +1,316 @@
+// State of the REPL kept from one command to the next
+
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::Algo;
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::parser::{parse_key_value_text, parse_text_literal};
+use crate::policy::Policy;
+use crate::registry;
+
+/// What the session remembers whatever cipher is selected; the key, the
+/// message, the alphabet and the policy follow to the next cipher.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub algo: Algo,
+    pub key: Option<KeyValue>,
+    pub message: String,
+    pub alphabet: Alphabet,
+    pub policy: Policy,
+    /// Output of the last encryption, decryption or attack.
+    pub last: Option<String>,
+    /// Files being run by `source`, outermost first; never saved.
+    pub sources: Vec<String>,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session {
+            algo: Algo::Empty,
+            key: None,
+            message: "".to_string(),
+            alphabet: Alphabet::default(),
+            policy: Policy::default(),
+            last: None,
+            sources: vec![],
+        }
+    }
+
+    /// Select the cipher `name`, giving it the alphabet, the policy, the
+    /// message and the key of the session when it accepts them; a key it
+    /// doesn't accept stays in the session for the next cipher.
+    pub fn select(&self, name: &str) -> Result<Session, CryptoError> {
+        let cipher = (registry::find(name)?.build)();
+        let cipher = cipher.set_alphabet(&self.alphabet).unwrap_or(cipher)
+            .set_policy(self.policy)
+            .set_message(&self.message);
+        let cipher = match &self.key {
+            Some(k) => cipher.set_key(k).unwrap_or(cipher),
+            None => cipher
+        };
+        Ok(Session { algo: Algo::Cipher(cipher), ..self.clone() })
+    }
+
+    pub fn set_key(&self, key: KeyValue) -> Result<Session, CryptoError> {
+        Ok(Session {
+            algo: self.algo.set_key(key.clone())?,
+            key: Some(key),
+            ..self.clone()
+        })
+    }
+
+    pub fn set_message(&self, message: &str) -> Session {
+        Session {
+            algo: match &self.algo {
+                Algo::Cipher(c) => Algo::Cipher(c.set_message(message)),
+                algo => algo.clone()
+            },
+            message: message.to_string(),
+            ..self.clone()
+        }
+    }
+
+    pub fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Session, CryptoError> {
+        let algo = match self.algo {
+            Algo::Cipher(_) => self.algo.set_alphabet(alphabet)?,
+            _ => self.algo.clone()
+        };
+        Ok(Session { algo, alphabet: alphabet.clone(), ..self.clone() })
+    }
+
+    /// Options of the policy are kept by the session, the others belong to the cipher.
+    pub fn set_option(&self, option: &str, value: &str) -> Result<Session, CryptoError> {
+        if Policy::has_option(option) {
+            let policy = self.policy.set(option, value)?;
+            let algo = match self.algo {
+                Algo::Cipher(_) => self.algo.set_option(option, value)?,
+                _ => self.algo.clone()
+            };
+            Ok(Session { algo, policy, ..self.clone() })
+        } else {
+            Ok(Session { algo: self.algo.set_option(option, value)?, ..self.clone() })
+        }
+    }
+
+    /// Remember the output of a command.
+    pub fn record(&self, res: &Result<String, CryptoError>) -> Session {
+        match res {
+            Ok(output) => Session { last: Some(output.clone()), ..self.clone() },
+            Err(_) => self.clone()
+        }
+    }
+
+    /// The alphabet in use: the cipher's, which may not support the session's,
+    /// or the session's for a cipher without alphabet.
+    pub fn get_alphabet(&self) -> Alphabet {
+        self.algo.get_alphabet().unwrap_or_else(|| self.alphabet.clone())
+    }
+
+    pub fn get_key(&self) -> String {
+        match &self.key {
+            Some(k) if self.algo.set_key(k.clone()).is_ok() => self.algo.get_key(),
+            Some(k) => format!("{} (not used by {})", k.syntax(), self.algo.get_algo()),
+            None => "[not defined]".to_string()
+        }
+    }
+
+    /// The session as `name = value` lines, the texts quoted and escaped.
+    pub fn save(&self) -> String {
+        let mut lines = vec!["# cryptography session".to_string()];
+        if let Algo::Cipher(_) = self.algo {
+            lines.push(format!("algo = {}", self.algo.get_algo()));
+        }
+        let options = self.algo.options();
+        if !options.is_empty() {
+            lines.push(format!("options = {}", options.iter()
+                .map(|(option, value)| format!("{} {}", option, value))
+                .collect::<Vec<String>>()
+                .join(", ")));
+        }
+        if let Some(key) = &self.key {
+            lines.push(format!("key = {}", key.syntax()));
+        }
+        lines.push(format!("message = {:?}", self.message));
+        lines.push(match self.alphabet.name() {
+            "custom" => format!("alphabet = {:?}", self.alphabet.letters().iter().collect::<String>()),
+            name => format!("alphabet = {}", name)
+        });
+        lines.push(format!("policy = {}", self.policy.describe()));
+        if let Some(last) = &self.last {
+            lines.push(format!("last = {:?}", last));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// Read a session written by [`Session::save`]; blank lines and lines
+    /// starting with `#` are ignored.
+    pub fn load(text: &str) -> Result<Session, CryptoError> {
+        let at = |line: usize| move |e: CryptoError| CryptoError::InvalidSession { line, reason: e.to_string() };
+        let mut session = Session::new();
+        let mut algo = None;
+        let mut options = None;
+        for (i, line) in text.lines().enumerate() {
+            let error = |reason: String| CryptoError::InvalidSession { line: i + 1, reason };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, value) = line.split_once('=')
+                .map(|(n, v)| (n.trim(), v.trim()))
+                .ok_or_else(|| error("expected name = value".to_string()))?;
+            match name {
+                "algo" => algo = Some((i + 1, value.to_string())),
+                "options" => options = Some((i + 1, value.to_string())),
+                "key" => session.key = Some(parse_key_value_text(value).map_err(|e| error(e.to_string()))?),
+                "message" => session.message = unquote(value).ok_or_else(|| error(format!("invalid text {}", value)))?,
+                "last" => session.last = Some(unquote(value).ok_or_else(|| error(format!("invalid text {}", value)))?),
+                "alphabet" => session.alphabet = match unquote(value) {
+                    Some(letters) => Alphabet::custom(&letters).map_err(|e| error(e.to_string()))?,
+                    None => Alphabet::named(value).ok_or_else(|| error(format!("unknown alphabet {}", value)))?
+                },
+                "policy" => for setting in value.split(',') {
+                    let (option, v) = setting.trim().split_once(' ')
+                        .ok_or_else(|| error(format!("invalid option {}", setting)))?;
+                    session.policy = session.policy.set(option, v).map_err(|e| error(e.to_string()))?;
+                },
+                _ => return Err(error(format!("unknown entry {}", name)))
+            }
+        }
+        if let Some((line, name)) = algo {
+            let last = session.last.clone();
+            session = session.select(&name).map_err(at(line))?;
+            session.last = last;
+        }
+        if let Some((line, value)) = options {
+            let options = value.split(',')
+                .map(|setting| setting.trim().split_once(' ')
+                    .map(|(o, v)| (o.to_string(), v.to_string()))
+                    .ok_or_else(|| CryptoError::InvalidSession { line, reason: format!("invalid option {}", setting) }))
+                .collect::<Result<Vec<(String, String)>, CryptoError>>()?;
+            session.algo = session.algo.set_options(&options).map_err(at(line))?;
+        }
+        Ok(session)
+    }
+
+    pub fn get_status(&self) -> String {
+        format!("algo: {} | key: {} | message: {} | last: {} | alphabet: {} | {}",
+            self.algo.get_algo(),
+            self.get_key(),
+            self.message,
+            self.last.as_deref().unwrap_or("[none]"),
+            self.get_alphabet().describe(),
+            self.policy.describe())
+    }
+
+}
+
+/// The text of a string written with `{:?}`.
+fn unquote(s: &str) -> Option<String> {
+    parse_text_literal(s).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_before_select() {
+        let session = Session::new().set_message("hello").select("cesar").unwrap();
+        assert_eq!(session.algo.get_message(), "hello");
+    }
+
+    #[test]
+    fn test_key_follows() {
+        let session = Session::new().select("vigenere").unwrap()
+            .set_key(KeyValue::String("key".to_string())).unwrap();
+        assert_eq!(session.select("vig").unwrap().get_key(), "\"key\"");
+        let session = session.select("cesar").unwrap();
+        assert_eq!(session.get_key(), "\"key\" (not used by cesar)");
+        assert_eq!(session.key, Some(KeyValue::String("key".to_string())));
+        assert_eq!(session.select("vigenere").unwrap().get_key(), "\"key\"");
+    }
+
+    #[test]
+    fn test_status() {
+        let session = Session::new().select("vigenere").unwrap()
+            .set_key(KeyValue::String("bc".to_string())).unwrap()
+            .set_message("hello");
+        let res = session.algo.encrypt2();
+        let session = session.record(&res);
+        assert_eq!(
+            session.get_status(),
+            "algo: vigenere | key: \"bc\" | message: hello | last: igmnp | alphabet: lowercase (26 letters) | nonalpha pass, case on, keyletters on, language english");
+    }
+
+    #[test]
+    fn test_unquote() {
+        let text = "line \"one\"\n\ttwo \\ é \u{1}";
+        assert_eq!(unquote(&format!("{:?}", text)), Some(text.to_string()));
+        assert_eq!(unquote("no quotes"), None);
+        assert_eq!(unquote("\"a\"b\""), None);
+    }
+
+    #[test]
+    fn test_alphabet_of_stream_cipher() {
+        let session = Session::new().select("lfsr").unwrap().set_alphabet(&Alphabet::mixed()).unwrap();
+        assert_eq!(session.get_alphabet().name(), "mixed");
+        assert_eq!(session.select("cesar").unwrap().get_alphabet().name(), "mixed");
+    }
+
+    #[test]
+    fn test_save_load() {
+        let session = Session::new()
+            .set_alphabet(&Alphabet::custom("abcdefg").unwrap()).unwrap()
+            .set_option("case", "off").unwrap()
+            .select("hill").unwrap()
+            .set_key(KeyValue::Matrix(vec![vec![3, 3], vec![2, 5]])).unwrap()
+            .set_message("bad\ncafe");
+        let session = session.record(&Ok("two\nlines".to_string()));
+        let text = session.save();
+        assert!(text.contains("key = [[3,3],[2,5]]\n"), "{}", text);
+        let loaded = Session::load(&text).unwrap();
+        assert_eq!(loaded.save(), text);
+        assert_eq!(loaded.last, Some("two\nlines".to_string()));
+    }
+
+    #[test]
+    fn test_save_load_options() {
+        let session = Session::new().select("lfsr").unwrap()
+            .set_option("mode", "galois").unwrap()
+            .set_key(KeyValue::Seeded(0xace1, vec![16, 14, 13, 11])).unwrap()
+            .set_message("secret");
+        let text = session.save();
+        assert!(text.contains("options = mode galois\n"), "{}", text);
+        let loaded = Session::load(&text).unwrap();
+        assert_eq!(loaded.algo.options(), vec![("mode", "galois".to_string())]);
+        assert_eq!(loaded.algo.encrypt2(), session.algo.encrypt2());
+        let playfair = Session::new().select("playfair").unwrap().set_option("filler", "q").unwrap();
+        assert_eq!(Session::load(&playfair.save()).unwrap().save(), playfair.save());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        assert_eq!(
+            Session::load("# comment\nalgo = cesar\ncolour = blue").err(),
+            Some(CryptoError::InvalidSession { line: 3, reason: "unknown entry colour".to_string() }));
+        assert!(Session::load("message = unquoted").is_err());
+        assert!(matches!(Session::load("algo = cesr"), Err(CryptoError::InvalidSession { line: 1, .. })));
+        assert!(matches!(Session::load("algo = cesar\nkey = 1..x"), Err(CryptoError::InvalidSession { line: 2, .. })));
+        let unused = Session::load("# cesar\nalgo = cesar\nkey = \"abc\"").unwrap();
+        assert_eq!(unused.get_key(), "\"abc\" (not used by cesar)");
+    }
+
+    #[test]
+    fn test_policy_without_algorithm() {
+        let session = Session::new().set_option("case", "off").unwrap().select("cesar").unwrap();
+        assert!(!session.algo.get_policy().preserve_case);
+        assert!(Session::new().set_option("mode", "galois").is_err());
+    }
+
+}
@@ -0,0 +1,95 @@
+// Helpers shared by the stream ciphers: XOR with a keystream and hex output
+
+use crate::error::CryptoError;
+
+/// Pack a stream of bits into bytes, the first bit being the most significant.
+pub fn pack<I: Iterator<Item = u8>>(bits: &mut I) -> u8 {
+    (0..8).fold(0, |acc, _| (acc << 1) | (bits.next().unwrap_or(0) & 1))
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn from_hex(hex: &str) -> Result<Vec<u8>, CryptoError> {
+    let digits = hex.chars().filter(|c| !c.is_whitespace()).collect::<Vec<char>>();
+    if let Some(c) = digits.iter().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(CryptoError::InvalidCharacter(*c));
+    }
+    if digits.len() % 2 != 0 {
+        return Err(CryptoError::InvalidHex(hex.to_string()));
+    }
+    Ok(digits.chunks(2)
+        .map(|pair| pair.iter().fold(0, |acc, c| acc * 16 + c.to_digit(16).unwrap_or(0) as u8))
+        .collect())
+}
+
+/// The bits of `bytes`, the most significant first.
+pub fn unpack(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| (0..8).rev().map(move |i| (b >> i) & 1)).collect()
+}
+
+/// Read a string of `0` and `1`, ignoring spaces.
+pub fn bits(text: &str) -> Result<Vec<u8>, CryptoError> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c {
+            '0' => Ok(0),
+            '1' => Ok(1),
+            _ => Err(CryptoError::InvalidCharacter(c))
+        })
+        .collect()
+}
+
+/// The keystream bits used to encrypt `plain` into the hex ciphertext `hex`.
+pub fn keystream(hex: &str, plain: &str) -> Result<Vec<u8>, CryptoError> {
+    let ct = from_hex(hex)?;
+    Ok(unpack(&ct.iter().zip(plain.bytes()).map(|(c, p)| c ^ p).collect::<Vec<u8>>()))
+}
+
+/// XOR `data` with the bytes packed from `bits`.
+pub fn xor<I: Iterator<Item = u8>>(data: &[u8], mut bits: I) -> Vec<u8> {
+    data.iter().map(|b| b ^ pack(&mut bits)).collect()
+}
+
+/// Encrypt the UTF-8 bytes of `msg`, as hex.
+pub fn encrypt<I: Iterator<Item = u8>>(msg: &str, bits: I) -> String {
+    to_hex(&xor(msg.as_bytes(), bits))
+}
+
+/// Decrypt a hex ciphertext, as text when it is valid UTF-8 and otherwise as
+/// hex after a `hex:` prefix, as with a wrong key.
+pub fn decrypt<I: Iterator<Item = u8>>(hex: &str, bits: I) -> Result<String, CryptoError> {
+    let plain = xor(&from_hex(hex)?, bits);
+    Ok(String::from_utf8(plain).unwrap_or_else(|e| format!("hex:{}", to_hex(e.as_bytes()))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(to_hex(&[0, 15, 255]), "000fff");
+        assert_eq!(from_hex("000fFF"), Ok(vec![0, 15, 255]));
+        assert_eq!(from_hex("0g"), Err(CryptoError::InvalidCharacter('g')));
+        assert_eq!(from_hex("abc"), Err(CryptoError::InvalidHex("abc".to_string())));
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(unpack(&[0xa1]), vec![1, 0, 1, 0, 0, 0, 0, 1]);
+        assert_eq!(bits("10 01"), Ok(vec![1, 0, 0, 1]));
+        assert_eq!(bits("102"), Err(CryptoError::InvalidCharacter('2')));
+        assert_eq!(keystream("be", "A"), Ok(vec![1; 8]));
+    }
+
+    #[test]
+    fn test_xor() {
+        let ones = std::iter::repeat(1);
+        assert_eq!(encrypt("A", ones.clone()), "be");
+        assert_eq!(decrypt("be", ones.clone()), Ok("A".to_string()));
+        assert_eq!(decrypt("00be", ones), Ok("hex:ff41".to_string()));
+    }
+
+}
@@ -1,30 +1,126 @@
 // tools for cryptography
 
-pub fn get_alphabet() -> Vec<(char, i8)> {
-    vec![ ('a', 0), ('b', 1), ('c', 2), ('d', 3), ('e', 4),
-        ('f', 5), ('g', 6), ('h', 7), ('i', 8), ('j', 9),
-        ('k', 10), ('l', 11), ('m', 12), ('n', 13), ('o', 14),
-        ('p', 15), ('q', 16), ('r', 17), ('s', 18), ('t', 19),
-        ('u', 20), ('v', 21), ('w', 22), ('x', 23), ('y', 24),
-        ('z', 25) ]
-}
-
-pub fn letter_to_number(c: char) -> i8 {
-    let alphabet =  get_alphabet();
-    let mut res = alphabet.iter().filter(|x| x.0 == c);
-    if let Some(couple) = res.next() {
-        couple.1
-    } else {
-        -1
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// An integer modulo `modulus`, always kept in `0..modulus`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Zmod {
+    value: i64,
+    modulus: i64
+}
+
+impl Zmod {
+    pub fn new(value: i64, modulus: i64) -> Zmod {
+        Zmod { value: value.rem_euclid(modulus), modulus }
+    }
+
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
+    pub fn modulus(&self) -> i64 {
+        self.modulus
+    }
+
+    fn with(&self, value: i64) -> Zmod {
+        Zmod::new(value, self.modulus)
+    }
+
+    /// Multiplicative inverse, when `value` is coprime with the modulus.
+    pub fn inverse(&self) -> Option<Zmod> {
+        let (g, x, _) = extended_gcd(self.value, self.modulus);
+        if g == 1 {
+            Some(self.with(x))
+        } else {
+            None
+        }
     }
 }
 
-pub fn number_to_letter(n: i8) -> char {
-    let alphabet = get_alphabet();
-    let mut res = alphabet.iter().filter(|x| x.1 == n);
-    if let Some(couple) = res.next() {
-        couple.0
+/// Returns `(g, x, y)` with `g = gcd(a, b) = a*x + b*y`.
+pub fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
     } else {
-        ' '
+        let (g, x, y) = extended_gcd(b, a.rem_euclid(b));
+        (g, y, x - a.div_euclid(b) * y)
     }
 }
+
+pub fn gcd(a: i64, b: i64) -> i64 {
+    extended_gcd(a, b).0
+}
+
+impl Add for Zmod {
+    type Output = Zmod;
+    fn add(self, other: Zmod) -> Zmod {
+        debug_assert_eq!(self.modulus, other.modulus);
+        self.with(self.value + other.value)
+    }
+}
+
+impl Sub for Zmod {
+    type Output = Zmod;
+    fn sub(self, other: Zmod) -> Zmod {
+        debug_assert_eq!(self.modulus, other.modulus);
+        self.with(self.value - other.value)
+    }
+}
+
+impl Mul for Zmod {
+    type Output = Zmod;
+    fn mul(self, other: Zmod) -> Zmod {
+        debug_assert_eq!(self.modulus, other.modulus);
+        self.with(((self.value as i128 * other.value as i128) % self.modulus as i128) as i64)
+    }
+}
+
+impl Neg for Zmod {
+    type Output = Zmod;
+    fn neg(self) -> Zmod {
+        self.with(-self.value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zmod_euclidean() {
+        assert_eq!(Zmod::new(-2, 26).value(), 24);
+        assert_eq!(Zmod::new(300, 26).value(), 14);
+        assert_eq!(Zmod::new(i64::MIN, 26).value(), i64::MIN.rem_euclid(26));
+    }
+
+    #[test]
+    fn test_zmod_operations() {
+        let a = Zmod::new(20, 26);
+        let b = Zmod::new(10, 26);
+        assert_eq!((a + b).value(), 4);
+        assert_eq!((b - a).value(), 16);
+        assert_eq!((a * b).value(), 18);
+        assert_eq!((-a).value(), 6);
+        assert_eq!((-Zmod::new(0, 26)).value(), 0);
+    }
+
+    #[test]
+    fn test_zmod_inverse() {
+        assert_eq!(Zmod::new(5, 26).inverse(), Some(Zmod::new(21, 26)));
+        assert_eq!(Zmod::new(-3, 26).inverse(), Some(Zmod::new(17, 26)));
+        assert_eq!(Zmod::new(13, 26).inverse(), None);
+        for a in 0..26 {
+            if let Some(inv) = Zmod::new(a, 26).inverse() {
+                assert_eq!((Zmod::new(a, 26) * inv).value(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_gcd() {
+        assert_eq!(gcd(12, 18), 6);
+        assert_eq!(gcd(-4, 26), 2);
+        assert_eq!(gcd(7, 26), 1);
+    }
+
+}
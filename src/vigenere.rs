@@ -1,91 +1,207 @@
 // Implementing the vigenaire algorythm
 
-use crate::tools::{get_alphabet, letter_to_number, number_to_letter};
+use crate::alphabet::Alphabet;
+use crate::base_cryptography::{Key, Cipher};
+use crate::cryptanalysis::crack_vigenere;
+use crate::error::CryptoError;
+use crate::parser::commands::KeyValue;
+use crate::policy::Policy;
+use crate::registry::CipherDescriptor;
+use crate::tools::Zmod;
+
+pub const DESCRIPTOR: CipherDescriptor = CipherDescriptor {
+    name: "vigenere",
+    aliases: &["vig"],
+    key_type: "string",
+    description: "shift each letter by the matching letter of a keyword",
+    build: || Box::new(Vigenere { key: "".to_string(), message: "".to_string(), alphabet: Alphabet::default(), policy: Policy::default() }),
+};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct Vigenere {
     pub key: String,
-    pub message: String
+    pub message: String,
+    pub alphabet: Alphabet,
+    pub policy: Policy
 }
 
-fn inverse_key(key: &str) -> String {
+fn inverse_key(key: &str, alphabet: &Alphabet) -> Result<String, CryptoError> {
     key.chars()
-        .map(letter_to_number)
-        .map(|x| (26-x) % 26)
-        .map(number_to_letter)
-        .collect::<String>()
+        .map(|c| alphabet.letter_to_number(c)
+            .map(|x| -x)
+            .map(|x| alphabet.number_to_letter(x)))
+        .collect::<Result<String, CryptoError>>()
 }
 
+fn shift(msg: &str, key: &str, alphabet: &Alphabet, policy: &Policy) -> Result<String, CryptoError> {
+   let keys = key.chars().map(|c| alphabet.letter_to_number(c)).collect::<Result<Vec<Zmod>, CryptoError>>()?;
+   let base = keys.len();
+   if base == 0 {
+       return Err(CryptoError::EmptyKey);
+   }
+   policy.apply(msg, alphabet, |i, n| keys[i % base] + n)
+}
 
-impl Vigenere {
-    pub fn encrypt(&self, msg: &str, key: &str) -> String {
-       let keys = key.chars().map(letter_to_number).collect::<Vec<i8>>();
-       let base = keys.len();
-       msg.chars().map(letter_to_number)
-           .enumerate()
-           .map(|x| (keys[x.0 % base], x.1))
-           .map(|x| (x.0 + x.1) % 26_i8)
-           .map(number_to_letter).collect()
+impl Cipher for Vigenere {
+    fn name(&self) -> &'static str {
+        "vigenere"
     }
 
-    pub fn encrypt2(&self) -> String {
-        self.encrypt(&self.message, &self.key)
+    fn title(&self) -> &'static str {
+        "Vigenère"
     }
 
-    pub fn decrypt(&self, msg: &str, key: &str) -> String {
-        self.encrypt(msg, &inverse_key(key))
+    fn encrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        shift(msg, &self.key, &self.alphabet, &self.policy)
     }
 
-    pub fn decrypt2(&self) -> String {
-        self.decrypt(&self.message, &self.key)
+    fn decrypt(&self, msg: &str) -> Result<String, CryptoError> {
+        shift(msg, &inverse_key(&self.key, &self.alphabet)?, &self.alphabet, &self.policy)
     }
 
-    pub fn set_key(&self, key: &str) -> Vigenere {
-        Vigenere {
-            key: key.to_string(),
-            message: self.message.clone()
+    fn set_key(&self, key: &KeyValue) -> Result<Box<dyn Cipher>, CryptoError> {
+        let value = String::from_key(self.name(), key)?;
+        if value.is_empty() {
+            return Err(CryptoError::EmptyKey);
         }
+        inverse_key(&value, &self.alphabet)?;
+        Ok(Box::new(Vigenere {
+            key: value,
+            ..self.clone()
+        }))
+    }
+
+    fn describe(&self) -> String {
+        self.key.describe()
+    }
+
+    fn get_message(&self) -> String {
+        self.message.clone()
+    }
+
+    fn set_message(&self, message: &str) -> Box<dyn Cipher> {
+        Box::new(Vigenere {
+            message: message.to_string(),
+            ..self.clone()
+        })
+    }
+
+    fn get_alphabet(&self) -> Option<&Alphabet> {
+        Some(&self.alphabet)
+    }
+
+    fn set_alphabet(&self, alphabet: &Alphabet) -> Result<Box<dyn Cipher>, CryptoError> {
+        inverse_key(&self.key, alphabet)?;
+        Ok(Box::new(Vigenere {
+            alphabet: alphabet.clone(),
+            ..self.clone()
+        }))
+    }
+
+    fn get_policy(&self) -> Policy {
+        self.policy
+    }
+
+    fn set_policy(&self, policy: Policy) -> Box<dyn Cipher> {
+        Box::new(Vigenere {
+            policy,
+            ..self.clone()
+        })
     }
 
-    pub fn get_key(&self) -> String {
-        self.key.clone()
+    fn crack(&self, ct: &str) -> Result<String, CryptoError> {
+        let crack = crack_vigenere(ct, &self.alphabet, &self.policy, 20)?;
+        let plain = Vigenere { key: crack.key.clone(), ..self.clone() }.decrypt(ct)?;
+        let kasiski = crack.kasiski.iter().take(5)
+            .map(|(l, c)| format!("{} ({})", l, c))
+            .collect::<Vec<String>>()
+            .join(", ");
+        Ok(format!("kasiski: {}\nfriedman: {:.2}\nkey length: {}\nkey: {}\nplaintext: {}",
+            kasiski, crack.friedman, crack.length, crack.key.describe(), plain))
     }
 
+    fn clone_box(&self) -> Box<dyn Cipher> {
+        Box::new(self.clone())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn vigenere(key: &str, message: &str) -> Vigenere {
+        Vigenere { key: key.to_string(), message: message.to_string(), alphabet: Alphabet::default(), policy: Policy::default() }
+    }
+
     #[test]
     fn test_vigenere_encrypt(){
-        let v = Vigenere { message: "hello".to_string().to_string(), key: "b".to_string() };
+        let v = vigenere("b", "hello");
         assert_eq!(
             v.encrypt2(),
-            "ifmmp".to_string());
+            Ok("ifmmp".to_string()));
     }
 
     #[test]
     fn test_vigenere_encrypt2(){
-        let v = Vigenere { message: "hello".to_string(), key: "bc".to_string() };
+        let v = vigenere("bc", "hello");
         assert_eq!(
             v.encrypt2(),
-            "igmnp".to_string());
+            Ok("igmnp".to_string()));
     }
 
     #[test]
     fn test_vigenere_decrypt(){
-        let v = Vigenere { message: "ifmmp".to_string(), key: "b".to_string() };
+        let v = vigenere("b", "ifmmp");
         assert_eq!(
             v.decrypt2(),
-            "hello".to_string());
+            Ok("hello".to_string()));
     }
 
     #[test]
     fn test_inverse_key() {
         assert_eq!(
-            inverse_key("a"),
-            "a".to_string());
+            inverse_key("a", &Alphabet::default()),
+            Ok("a".to_string()));
+    }
+
+    #[test]
+    fn test_vigenere_decrypt_encrypt_every_key() {
+        let m = "thequickbrownfoxjumpsoverthelazydog";
+        let letters = "abcdefghijklmnopqrstuvwxyz".chars().collect::<Vec<char>>();
+        for a in &letters {
+            for b in &letters {
+                let v = vigenere(&format!("{}{}z", a, b), "");
+                assert_eq!(
+                    v.decrypt(&v.encrypt(m).unwrap()),
+                    Ok(m.to_string()), "key {}", v.key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_vigenere_key_advance() {
+        let v = vigenere("lemon", "Attack at dawn!");
+        assert_eq!(v.encrypt2(), Ok("Lxfopv ef rnhr!".to_string()));
+        let v = Vigenere { policy: Policy::default().set("keyletters", "off").unwrap(), ..v };
+        assert_eq!(v.encrypt2(), Ok("Lxfopv mh oeib!".to_string()));
+        assert_eq!(v.decrypt(&v.encrypt2().unwrap()), Ok("Attack at dawn!".to_string()));
+    }
+
+    #[test]
+    fn test_vigenere_bad_key() {
+        let v = vigenere("", "hello");
+        assert_eq!(v.encrypt2(), Err(CryptoError::EmptyKey));
+        assert_eq!(
+            v.set_key(&KeyValue::String("b4".to_string())).err(),
+            Some(CryptoError::InvalidCharacter('4')));
+    }
+
+    #[test]
+    fn test_vigenere_set_alphabet() {
+        let v = vigenere("Key", "Hello");
+        assert!(v.set_alphabet(&Alphabet::lowercase()).is_err());
+        let v = v.set_alphabet(&Alphabet::mixed()).unwrap();
+        assert_eq!(v.decrypt(&v.encrypt2().unwrap()), Ok("Hello".to_string()));
     }
 
 